@@ -1,5 +1,3 @@
-use std::usize;
-
 use self::{
     bishop_attack_generators::{generate_bishop_attack_mask, generate_bishop_attacks_on_the_fly},
     magic_number_constants::{
@@ -40,6 +38,8 @@ lazy_static! {
     static ref KING_ATTACK_TABLE: [BoardSlice; 64] = generate_king_attack_table();
     static ref BISHOP_ATTACK_TABLE: Vec<[BoardSlice; 512]> = generate_bishop_attack_table();
     static ref ROOK_ATTACK_TABLE: Vec<[BoardSlice; 4096]> = generate_rook_attack_table();
+    static ref SQUARES_BETWEEN_TABLE: Vec<[BoardSlice; 64]> = generate_squares_between_table();
+    static ref LINE_TABLE: Vec<[BoardSlice; 64]> = generate_line_table();
 }
 
 #[allow(clippy::needless_range_loop)]
@@ -284,6 +284,55 @@ fn generate_rook_attack_table() -> Vec<[BoardSlice; 4096]> {
     attack_table
 }
 
+/// For every pair of squares sharing a rank, file or diagonal, the squares strictly between them.
+/// Unaligned pairs map to an empty slice.
+fn generate_squares_between_table() -> Vec<[BoardSlice; 64]> {
+    let mut between_table = vec![[BoardSlice(0); 64]; 64];
+
+    for orig in Square::iter() {
+        for dest in Square::iter() {
+            let orig_slice = BoardSlice(1 << orig as usize);
+            let dest_slice = BoardSlice(1 << dest as usize);
+
+            if generate_rook_attacks_on_the_fly(orig, BoardSlice(0)).0 & dest_slice.0 != 0 {
+                between_table[orig as usize][dest as usize] =
+                    generate_rook_attacks_on_the_fly(orig, dest_slice)
+                        & generate_rook_attacks_on_the_fly(dest, orig_slice);
+            } else if generate_bishop_attacks_on_the_fly(orig, BoardSlice(0)).0 & dest_slice.0 != 0
+            {
+                between_table[orig as usize][dest as usize] =
+                    generate_bishop_attacks_on_the_fly(orig, dest_slice)
+                        & generate_bishop_attacks_on_the_fly(dest, orig_slice);
+            }
+        }
+    }
+    between_table
+}
+
+/// For every pair of aligned squares, the full edge-to-edge line through both of them.
+/// Unaligned pairs map to an empty slice.
+fn generate_line_table() -> Vec<[BoardSlice; 64]> {
+    let mut line_table = vec![[BoardSlice(0); 64]; 64];
+
+    for orig in Square::iter() {
+        for dest in Square::iter() {
+            let ends = BoardSlice(1 << orig as usize | 1 << dest as usize);
+            let orig_rook_rays = generate_rook_attacks_on_the_fly(orig, BoardSlice(0));
+            let orig_bishop_rays = generate_bishop_attacks_on_the_fly(orig, BoardSlice(0));
+
+            if orig_rook_rays.0 & (1 << dest as usize) != 0 {
+                line_table[orig as usize][dest as usize] =
+                    (orig_rook_rays & generate_rook_attacks_on_the_fly(dest, BoardSlice(0))) | ends;
+            } else if orig_bishop_rays.0 & (1 << dest as usize) != 0 {
+                line_table[orig as usize][dest as usize] = (orig_bishop_rays
+                    & generate_bishop_attacks_on_the_fly(dest, BoardSlice(0)))
+                    | ends;
+            }
+        }
+    }
+    line_table
+}
+
 pub fn get_pawn_moves(color: Color, square: Square) -> BoardSlice {
     match color {
         Color::White => WHITE_PAWN_MOVE_TABLE[square as usize],
@@ -338,6 +387,16 @@ pub fn get_queen_attacks(square: Square, blockers: BoardSlice) -> BoardSlice {
     BoardSlice(get_bishop_attacks(square, blockers).0 | get_rook_attacks(square, blockers).0)
 }
 
+/// Squares strictly between `orig` and `dest`, or an empty slice if they are not aligned.
+pub fn get_squares_between(orig: Square, dest: Square) -> BoardSlice {
+    SQUARES_BETWEEN_TABLE[orig as usize][dest as usize]
+}
+
+/// The whole line through `orig` and `dest`, or an empty slice if they are not aligned.
+pub fn get_line(orig: Square, dest: Square) -> BoardSlice {
+    LINE_TABLE[orig as usize][dest as usize]
+}
+
 #[cfg(test)]
 pub mod tests {
 
@@ -558,4 +617,31 @@ pub mod tests {
             BoardSlice(0x10254386f385402)
         );
     }
+
+    #[test]
+    fn test_get_squares_between() {
+        assert_eq!(
+            get_squares_between(Square::A1, Square::D1),
+            BoardSlice(1 << Square::B1 as u64 | 1 << Square::C1 as u64)
+        );
+        assert_eq!(
+            get_squares_between(Square::H8, Square::E5),
+            BoardSlice(1 << Square::G7 as u64 | 1 << Square::F6 as u64)
+        );
+        assert_eq!(get_squares_between(Square::E4, Square::E5), BoardSlice(0));
+        assert_eq!(get_squares_between(Square::A1, Square::B3), BoardSlice(0));
+    }
+
+    #[test]
+    fn test_get_line() {
+        assert_eq!(
+            get_line(Square::B2, Square::D4),
+            BoardSlice(0x8040201008040201)
+        );
+        assert_eq!(
+            get_line(Square::C3, Square::C6),
+            BoardSlice(0x0404040404040404)
+        );
+        assert_eq!(get_line(Square::A1, Square::B3), BoardSlice(0));
+    }
 }
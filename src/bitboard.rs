@@ -1,8 +1,18 @@
 use crate::attack_tables::{
-    get_bishop_attacks, get_king_attacks, get_knight_attacks, get_pawn_attacks, get_queen_attacks,
-    get_rook_attacks,
+    get_bishop_attacks, get_king_attacks, get_knight_attacks, get_pawn_attacks, get_rook_attacks,
+    get_squares_between,
+};
+use crate::move_generation::{generate_legal_moves, MoveList};
+use crate::move_performing::perform_move;
+use crate::utils::{
+    _move::Move,
+    board_slice::BoardSlice,
+    enums::*,
+    errors::{FENParseError, MoveParseError, PositionError},
+};
+use crate::zobrist::{
+    get_black_to_move_key, get_castling_rights_key, get_en_passant_key, get_piece_key,
 };
-use crate::utils::{board_slice::BoardSlice, enums::*, errors::FENParseError};
 use int_enum::IntEnum;
 use std::fmt;
 use std::str::FromStr;
@@ -15,18 +25,65 @@ macro_rules! bitboard_piece_index {
     };
 }
 
+pub const STARTING_POSITION_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+const BACK_RANKS: u64 = 0xFF00_0000_0000_00FF;
+
+/// Rook squares of the castling rights in standard chess, indexed by `CastleMoves::index`.
+const STANDARD_CASTLING_ROOK_SQUARES: [Square; 4] =
+    [Square::H1, Square::A1, Square::H8, Square::A8];
+
+/// Squares of the two knights among the five squares left after placing the bishops and the
+/// queen, in Scharnagl's numbering of the Chess960 starting positions.
+const CHESS960_KNIGHT_PLACEMENTS: [(usize, usize); 10] = [
+    (0, 1),
+    (0, 2),
+    (0, 3),
+    (0, 4),
+    (1, 2),
+    (1, 3),
+    (1, 4),
+    (2, 3),
+    (2, 4),
+    (3, 4),
+];
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum FENParseMode {
+    /// Six whitespace-separated fields, as `FromStr` expects. Like every mode, rejects
+    /// positions without exactly one king per side or with the side not to move in check.
+    Standard,
+    /// Also reject positions that fail any other check of `Bitboard::validate`.
+    Strict,
+    /// Also accept leaving out the clocks or the en passant square along with them, as in EPD
+    /// records. Missing clocks default to `0 1`.
+    Lenient,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub struct Bitboard {
     pieces: [BoardSlice; 12],
 
     pub to_move: Color,
 
-    castling_rights: u8,
+    pub(crate) castling_rights: u8,
+
+    /// Square of the rook each castling right belongs to, indexed by `CastleMoves::index`.
+    pub(crate) castling_rook_squares: [Square; 4],
+
+    /// Chess960 rules: castling is encoded as the king capturing its own rook and the king and
+    /// rooks may start on any back rank square. Set when parsing a FEN that needs it.
+    pub chess960: bool,
 
+    /// Change through `set_en_passant_square`. The Zobrist key only takes the square into
+    /// account when it is read, so writing the field directly cannot desync it either.
     pub en_passant_square: Option<Square>,
 
     pub half_move_clock: usize,
     pub full_move_clock: usize,
+
+    /// Key of everything but the en passant file, which `get_zobrist_key` adds when it matters.
+    pub(crate) zobrist_key: u64,
 }
 
 impl Bitboard {
@@ -57,19 +114,38 @@ impl Bitboard {
         }
     }
 
-    pub fn is_square_attacked(&self, color: Color, square: Square) -> bool {
-        ((get_pawn_attacks(color.opposite(), square) & self.get_piece(color, Piece::Pawn))
+    pub fn get_piece_at(&self, square: Square) -> Option<(Color, Piece)> {
+        self.pieces
+            .iter()
+            .position(|slice| slice.0 & (1 << square as usize) != 0)
+            .map(|index| {
+                let color = if index < 6 {
+                    Color::White
+                } else {
+                    Color::Black
+                };
+                (color, Piece::iter().nth(index % 6).unwrap())
+            })
+    }
+
+    /// Pieces of `color` attacking `square`, with sliding attacks computed against `occupancy`
+    /// rather than the current board so callers can look through pieces that are about to move.
+    pub fn get_attackers(&self, color: Color, square: Square, occupancy: BoardSlice) -> BoardSlice {
+        (get_pawn_attacks(color.opposite(), square) & self.get_piece(color, Piece::Pawn))
             | (get_knight_attacks(square) & self.get_piece(color, Piece::Knight))
-            | (get_bishop_attacks(square, self.get_all_pieces())
-                & self.get_piece(color, Piece::Bishop))
-            | (get_rook_attacks(square, self.get_all_pieces())
-                & self.get_piece(color, Piece::Rook))
-            | (get_queen_attacks(square, self.get_all_pieces())
-                & self.get_piece(color, Piece::Queen))
-            | (get_king_attacks(square) & self.get_piece(color, Piece::King)))
-        .0 != 0
+            | (get_bishop_attacks(square, occupancy)
+                & (self.get_piece(color, Piece::Bishop) | self.get_piece(color, Piece::Queen)))
+            | (get_rook_attacks(square, occupancy)
+                & (self.get_piece(color, Piece::Rook) | self.get_piece(color, Piece::Queen)))
+            | (get_king_attacks(square) & self.get_piece(color, Piece::King))
     }
 
+    pub fn is_square_attacked(&self, color: Color, square: Square) -> bool {
+        self.get_attackers(color, square, self.get_all_pieces()).0 != 0
+    }
+
+    /// Square of the king of `color`. Panics if there is no such king, which parsing a FEN rules
+    /// out.
     pub fn get_king_square(&self, color: Color) -> Square {
         Square::from_int(self.get_piece(color, Piece::King).0.trailing_zeros() as u8).unwrap()
     }
@@ -78,32 +154,335 @@ impl Bitboard {
         self.is_square_attacked(color.opposite(), self.get_king_square(color))
     }
 
+    /// Whether a pseudo-legal move, as produced by `generate_pseudo_legal_moves`, is legal.
+    /// The move is played with `perform_move` and rejected if it leaves the mover's king in check;
+    /// castling is additionally rejected out of check or through an attacked square.
+    pub fn is_legal(&self, _move: Move) -> bool {
+        let color = self.to_move;
+
+        if let Some(cm) = self.get_castle(&_move) {
+            let (king_dest, _, _) = self.get_castling_squares(cm);
+            if self.is_king_in_check(color)
+                || get_squares_between(_move.orig, king_dest)
+                    .iter()
+                    .any(|square| self.is_square_attacked(color.opposite(), square))
+            {
+                return false;
+            }
+        }
+
+        match perform_move(self, &_move) {
+            Ok(bitboard) => !bitboard.is_king_in_check(color),
+            Err(_) => false,
+        }
+    }
+
+    /// Parse a UCI long algebraic move such as `e7e8q` and check that it is legal here.
+    pub fn parse_uci_move(&self, move_str: &str) -> Result<Move, MoveParseError> {
+        let _move = move_str.parse::<Move>()?;
+
+        let mut legal_moves = MoveList::new();
+        generate_legal_moves(self, &mut legal_moves);
+        if legal_moves.contains(&_move) {
+            Ok(_move)
+        } else {
+            Err(MoveParseError::IllegalMove(_move))
+        }
+    }
+
+    /// Check that the position could arise in a game: one king per side, no pawns on the back
+    /// ranks, no square holding two pieces, an en passant square behind a pawn that has just
+    /// double pushed, castling rights backed by king and rook on their home squares, and the side
+    /// not to move not in check.
+    pub fn validate(&self) -> Vec<PositionError> {
+        let mut errors = Vec::new();
+
+        for color in Color::iter() {
+            let king_count = self.get_piece(color, Piece::King).0.count_ones();
+            if king_count != 1 {
+                errors.push(PositionError::IncorrectKingCount(color, king_count));
+            }
+        }
+
+        let pawns =
+            self.get_piece(Color::White, Piece::Pawn) | self.get_piece(Color::Black, Piece::Pawn);
+        for square in (pawns & BoardSlice(BACK_RANKS)).iter() {
+            errors.push(PositionError::PawnOnBackRank(square));
+        }
+
+        let mut occupied = BoardSlice(0);
+        let mut overlapping = BoardSlice(0);
+        for &slice in self.pieces.iter() {
+            overlapping = overlapping | (occupied & slice);
+            occupied = occupied | slice;
+        }
+        for square in overlapping.iter() {
+            errors.push(PositionError::OverlappingPieces(square));
+        }
+
+        if let Some(square) = self.en_passant_square {
+            let (en_passant_rank, pushed_pawn_offset) = match self.to_move {
+                Color::White => (5, -8),
+                Color::Black => (2, 8),
+            };
+            let is_valid = square as u8 / 8 == en_passant_rank
+                && self.get_piece_at(square).is_none()
+                && self
+                    .get_piece_at(
+                        Square::from_int((square as i8 - pushed_pawn_offset) as u8).unwrap(),
+                    )
+                    .is_none()
+                && self.get_piece_at(
+                    Square::from_int((square as i8 + pushed_pawn_offset) as u8).unwrap(),
+                ) == Some((self.to_move.opposite(), Piece::Pawn));
+            if !is_valid {
+                errors.push(PositionError::InvalidEnPassantSquare(square));
+            }
+        }
+
+        // Outside Chess960 the king must stand on the e-file, otherwise anywhere on the back rank
+        // between the rooks.
+        for cm in CastleMoves::iter() {
+            let color = cm.color();
+            let rook_square = self.castling_rook_squares[cm.index()];
+            let king_slice =
+                self.get_piece(color, Piece::King).0 & (0xFF << (rook_square as u8 & !7));
+            let is_valid = match king_slice.count_ones() {
+                1 => {
+                    let king_file = king_slice.trailing_zeros() as u8 % 8;
+                    let rook_file = rook_square as u8 % 8;
+                    (self.chess960 || king_file == File::EFile as u8)
+                        && (rook_file > king_file) == cm.is_kingside()
+                        && self.get_piece_at(rook_square) == Some((color, Piece::Rook))
+                }
+                _ => false,
+            };
+            if self.has_castling_right(cm) && !is_valid {
+                errors.push(PositionError::InvalidCastlingRights(cm));
+            }
+        }
+
+        let opponent = self.to_move.opposite();
+        if self.get_piece(opponent, Piece::King).0.count_ones() == 1
+            && self.is_king_in_check(opponent)
+        {
+            errors.push(PositionError::OpponentInCheck);
+        }
+
+        errors
+    }
+
+    /// Parse a FEN according to `mode`. `FromStr` uses `FENParseMode::Standard`.
+    pub fn from_fen(fen: &str, mode: FENParseMode) -> Result<Bitboard, FENParseError> {
+        let bitboard = parse_fen(fen, mode == FENParseMode::Lenient)?;
+        let mut errors = bitboard.validate();
+        if mode != FENParseMode::Strict {
+            // Move generation cannot work without the kings, nor search a position where the
+            // king can be captured
+            errors.retain(|error| {
+                matches!(
+                    error,
+                    PositionError::IncorrectKingCount(..) | PositionError::OpponentInCheck
+                )
+            });
+        }
+        if !errors.is_empty() {
+            return Err(FENParseError::InvalidPosition(errors));
+        }
+        Ok(bitboard)
+    }
+
+    /// Starting position number `index` of Chess960, in Scharnagl's numbering from 0 to 959.
+    /// Position 518 is the standard starting position.
+    pub fn from_chess960_index(index: usize) -> Option<Bitboard> {
+        if index >= 960 {
+            return None;
+        }
+
+        let mut back_rank = [None; 8];
+        let (index, light_bishop) = (index / 4, index % 4);
+        back_rank[light_bishop * 2 + 1] = Some('b');
+        let (index, dark_bishop) = (index / 4, index % 4);
+        back_rank[dark_bishop * 2] = Some('b');
+
+        let mut place_on_empty = |nth: usize, piece: char| {
+            let file = (0..8)
+                .filter(|&file| back_rank[file].is_none())
+                .nth(nth)
+                .unwrap();
+            back_rank[file] = Some(piece);
+        };
+        let (index, queen) = (index / 6, index % 6);
+        place_on_empty(queen, 'q');
+        let (first_knight, second_knight) = CHESS960_KNIGHT_PLACEMENTS[index];
+        place_on_empty(second_knight, 'n');
+        place_on_empty(first_knight, 'n');
+        for piece in ['r', 'k', 'r'] {
+            place_on_empty(0, piece);
+        }
+
+        let black_pieces: String = back_rank.iter().map(|piece| piece.unwrap()).collect();
+        let rook_files: String = (0..8)
+            .rev()
+            .filter(|&file| back_rank[file] == Some('r'))
+            .map(|file| (b'a' + file as u8) as char)
+            .collect();
+        let fen = format!(
+            "{}/pppppppp/8/8/8/8/PPPPPPPP/{} w {}{} - 0 1",
+            black_pieces,
+            black_pieces.to_ascii_uppercase(),
+            rook_files.to_ascii_uppercase(),
+            rook_files
+        );
+
+        let mut bitboard = fen.parse::<Bitboard>().unwrap();
+        bitboard.chess960 = true;
+        Some(bitboard)
+    }
+
     pub fn has_castling_right(&self, cm: CastleMoves) -> bool {
         self.castling_rights & (cm as u8) != 0
     }
 
+    pub fn get_castling_rook_square(&self, cm: CastleMoves) -> Square {
+        self.castling_rook_squares[cm.index()]
+    }
+
+    /// King destination, rook origin and rook destination of castle `cm`. Wherever they start,
+    /// the king ends up on the g- or c-file and the rook on the f- or d-file.
+    pub fn get_castling_squares(&self, cm: CastleMoves) -> (Square, Square, Square) {
+        let rank_start = match cm.color() {
+            Color::White => 0,
+            Color::Black => 56,
+        };
+        let (king_file, rook_file) = if cm.is_kingside() {
+            (File::GFile, File::FFile)
+        } else {
+            (File::CFile, File::DFile)
+        };
+        (
+            Square::from_int(rank_start + king_file as u8).unwrap(),
+            self.castling_rook_squares[cm.index()],
+            Square::from_int(rank_start + rook_file as u8).unwrap(),
+        )
+    }
+
+    /// The castle `_move` stands for, if any: the king moving two squares along the back rank,
+    /// or in Chess960 mode the king capturing its own rook.
+    pub fn get_castle(&self, _move: &Move) -> Option<CastleMoves> {
+        let color = self.to_move;
+        if self.get_piece(color, Piece::King).0 & (1 << _move.orig as usize) == 0
+            || _move.orig as u8 / 8 != _move.dest as u8 / 8
+        {
+            return None;
+        }
+
+        let kingside = if self.chess960 {
+            if self.get_piece(color, Piece::Rook).0 & (1 << _move.dest as usize) == 0 {
+                return None;
+            }
+            _move.dest as u8 > _move.orig as u8
+        } else {
+            match _move.dest as i8 - _move.orig as i8 {
+                2 => true,
+                -2 => false,
+                _ => return None,
+            }
+        };
+        Some(CastleMoves::new(color, kingside))
+    }
+
     pub fn add_piece(&mut self, color: Color, piece: Piece, square: Square) {
         self.pieces[bitboard_piece_index!(color, piece)].0 |= 1 << square as usize;
+        self.zobrist_key ^= get_piece_key(color, piece, square);
     }
 
     pub fn remove_piece(&mut self, color: Color, piece: Piece, square: Square) {
         self.pieces[bitboard_piece_index!(color, piece)].0 &= !(1 << square as usize);
+        self.zobrist_key ^= get_piece_key(color, piece, square);
     }
 
     pub fn move_piece(&mut self, color: Color, piece: Piece, orig: Square, dest: Square) {
         self.pieces[bitboard_piece_index!(color, piece)].0 &= !(1 << orig as usize);
         self.pieces[bitboard_piece_index!(color, piece)].0 |= 1 << dest as usize;
+        self.zobrist_key ^= get_piece_key(color, piece, orig) ^ get_piece_key(color, piece, dest);
     }
 
     pub fn toggle_move(&mut self) {
         self.to_move = self.to_move.opposite();
+        self.zobrist_key ^= get_black_to_move_key();
     }
 
+    /// Remove a castling right. The rook square is reset to its standard one so positions with
+    /// the same rights compare equal however they were reached.
     pub fn remove_castling_right(&mut self, cm: CastleMoves) {
+        self.zobrist_key ^= get_castling_rights_key(self.castling_rights);
         self.castling_rights &= !(cm as u8);
+        self.castling_rook_squares[cm.index()] = STANDARD_CASTLING_ROOK_SQUARES[cm.index()];
+        self.zobrist_key ^= get_castling_rights_key(self.castling_rights);
+    }
+
+    /// Set the square a pawn may be captured en passant on.
+    pub fn set_en_passant_square(&mut self, en_passant_square: Option<Square>) {
+        self.en_passant_square = en_passant_square;
+    }
+
+    /// The en passant square if a pawn of the side to move can capture on it, ignoring pins.
+    /// Only then does it tell the position apart from the same one without it.
+    pub fn get_capturable_en_passant_square(&self) -> Option<Square> {
+        self.en_passant_square.filter(|&square| {
+            (get_pawn_attacks(self.to_move.opposite(), square)
+                & self.get_piece(self.to_move, Piece::Pawn))
+            .0 != 0
+        })
     }
 
+    /// 64-bit key of the position, covering pieces, side to move, castling rights and the en
+    /// passant file when a pawn can capture en passant, but not the move clocks. Maintained
+    /// incrementally as the board changes.
+    pub fn get_zobrist_key(&self) -> u64 {
+        match self.get_capturable_en_passant_square() {
+            Some(square) => self.zobrist_key ^ get_en_passant_key(square),
+            None => self.zobrist_key,
+        }
+    }
+
+    /// Zobrist key of the position computed from scratch.
+    pub fn compute_zobrist_key(&self) -> u64 {
+        match self.get_capturable_en_passant_square() {
+            Some(square) => self.compute_board_key() ^ get_en_passant_key(square),
+            None => self.compute_board_key(),
+        }
+    }
+
+    /// Zobrist key computed from scratch without the en passant file, as kept in `zobrist_key`.
+    pub(crate) fn compute_board_key(&self) -> u64 {
+        let mut key = 0;
+        for color in Color::iter() {
+            for piece in Piece::iter() {
+                for square in self.get_piece(color, piece).iter() {
+                    key ^= get_piece_key(color, piece, square);
+                }
+            }
+        }
+        if self.to_move == Color::Black {
+            key ^= get_black_to_move_key();
+        }
+        key ^ get_castling_rights_key(self.castling_rights)
+    }
+
+    /// FEN of the position. Chess960 castling rights are written in X-FEN: `K` or `Q` for the
+    /// outermost rook on that side of the king, the rook's file otherwise.
     pub fn to_str(&self) -> String {
+        self.format_fen(false)
+    }
+
+    /// FEN of the position with Shredder-FEN castling rights, which always name the rook's file.
+    pub fn to_shredder_fen(&self) -> String {
+        self.format_fen(true)
+    }
+
+    fn format_fen(&self, shredder: bool) -> String {
         let mut fen = String::new();
 
         for i in (0..8).rev() {
@@ -156,17 +535,28 @@ impl Bitboard {
         if self.castling_rights == 0 {
             fen.push('-');
         } else {
-            if self.castling_rights & CastleMoves::WhiteKingsideCastle as u8 != 0 {
-                fen.push('K');
-            }
-            if self.castling_rights & CastleMoves::WhiteQueensideCastle as u8 != 0 {
-                fen.push('Q');
-            }
-            if self.castling_rights & CastleMoves::BlackKingsideCastle as u8 != 0 {
-                fen.push('k');
-            }
-            if self.castling_rights & CastleMoves::BlackQueensideCastle as u8 != 0 {
-                fen.push('q');
+            for cm in CastleMoves::iter().filter(|&cm| self.has_castling_right(cm)) {
+                let rook_square = self.castling_rook_squares[cm.index()];
+                let rook_file = rook_square as u8 % 8;
+                let rooks = self.get_piece(cm.color(), Piece::Rook).0 >> (rook_square as u8 & !7);
+                let outer_files = if cm.is_kingside() {
+                    0xFF & !((2 << rook_file) - 1)
+                } else {
+                    (1 << rook_file) - 1
+                };
+                let symbol = if !self.chess960 || (!shredder && rooks & outer_files == 0) {
+                    if cm.is_kingside() {
+                        'k'
+                    } else {
+                        'q'
+                    }
+                } else {
+                    (b'a' + rook_file) as char
+                };
+                fen.push(match cm.color() {
+                    Color::White => symbol.to_ascii_uppercase(),
+                    Color::Black => symbol,
+                });
             }
         }
 
@@ -191,122 +581,241 @@ impl FromStr for Bitboard {
     type Err = FENParseError;
 
     fn from_str(fen: &str) -> Result<Bitboard, FENParseError> {
-        let fen_parts: Vec<&str> = fen.split_whitespace().collect();
-        if fen_parts.len() != 6 {
-            return Err(FENParseError::IncorrectPartsCount(fen_parts.len()));
-        }
+        Bitboard::from_fen(fen, FENParseMode::Standard)
+    }
+}
 
-        let fen_board: Vec<&str> = fen_parts[0].split('/').collect();
-        if fen_board.len() != 8 {
-            return Err(FENParseError::IncorrectBoardLength(fen_board.len()));
+/// Split a FEN on runs of whitespace into its fields along with the character offset each
+/// starts at.
+fn split_fen_fields(fen: &str) -> Vec<(usize, String)> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut field_offset = 0;
+    for (offset, c) in fen.chars().enumerate() {
+        if !c.is_whitespace() {
+            if field.is_empty() {
+                field_offset = offset;
+            }
+            field.push(c);
+        } else if !field.is_empty() {
+            fields.push((field_offset, std::mem::take(&mut field)));
         }
+    }
+    if !field.is_empty() {
+        fields.push((field_offset, field));
+    }
+    fields
+}
 
-        let mut pieces = [BoardSlice(0); 12];
-        fen_board
-            .iter()
-            .rev()
-            .enumerate()
-            .try_for_each::<_, Result<(), FENParseError>>(|(row_index, &row)| {
-                match row.chars().try_fold(row_index * 8, |acc, c| {
-                    if acc >= (row_index + 1) * 8 {
-                        return Err(FENParseError::IncorrectBoardRowLength(row_index + 1));
-                    }
-                    match c {
-                        'P' => {
-                            pieces[bitboard_piece_index!(Color::White, Piece::Pawn)].0 |= 1 << acc
-                        }
-                        'N' => {
-                            pieces[bitboard_piece_index!(Color::White, Piece::Knight)].0 |= 1 << acc
-                        }
-                        'B' => {
-                            pieces[bitboard_piece_index!(Color::White, Piece::Bishop)].0 |= 1 << acc
-                        }
-                        'R' => {
-                            pieces[bitboard_piece_index!(Color::White, Piece::Rook)].0 |= 1 << acc
-                        }
-                        'Q' => {
-                            pieces[bitboard_piece_index!(Color::White, Piece::Queen)].0 |= 1 << acc
-                        }
-                        'K' => {
-                            pieces[bitboard_piece_index!(Color::White, Piece::King)].0 |= 1 << acc
-                        }
-                        'p' => {
-                            pieces[bitboard_piece_index!(Color::Black, Piece::Pawn)].0 |= 1 << acc
-                        }
-                        'n' => {
-                            pieces[bitboard_piece_index!(Color::Black, Piece::Knight)].0 |= 1 << acc
-                        }
-                        'b' => {
-                            pieces[bitboard_piece_index!(Color::Black, Piece::Bishop)].0 |= 1 << acc
-                        }
-                        'r' => {
-                            pieces[bitboard_piece_index!(Color::Black, Piece::Rook)].0 |= 1 << acc
-                        }
-                        'q' => {
-                            pieces[bitboard_piece_index!(Color::Black, Piece::Queen)].0 |= 1 << acc
-                        }
-                        'k' => {
-                            pieces[bitboard_piece_index!(Color::Black, Piece::King)].0 |= 1 << acc
-                        }
-                        '1'..='9' => {
-                            if (c.to_digit(10).unwrap() as usize + acc) > (row_index + 1) * 8 {
-                                return Err(FENParseError::IncorrectBoardRowLength(row_index + 1));
-                            }
-                            return Ok(acc + c.to_digit(10).unwrap() as usize);
-                        }
-                        other => return Err(FENParseError::IncorrectBoard(other)),
-                    };
-                    Ok(acc + 1)
-                }) {
-                    Ok(_) => Ok(()),
-                    Err(err) => Err(err),
-                }
-            })?;
+fn get_fen_piece(c: char) -> Option<(Color, Piece)> {
+    let piece = match c.to_ascii_lowercase() {
+        'p' => Piece::Pawn,
+        'n' => Piece::Knight,
+        'b' => Piece::Bishop,
+        'r' => Piece::Rook,
+        'q' => Piece::Queen,
+        'k' => Piece::King,
+        _ => return None,
+    };
+    let color = if c.is_ascii_uppercase() {
+        Color::White
+    } else {
+        Color::Black
+    };
+    Some((color, piece))
+}
 
-        let to_move = match fen_parts[1] {
-            "w" => Color::White,
-            "b" => Color::Black,
-            _ => return Err(FENParseError::IncorrectToMove),
-        };
+fn parse_fen(fen: &str, lenient: bool) -> Result<Bitboard, FENParseError> {
+    let fields = split_fen_fields(fen);
+    let allowed_field_counts = if lenient { 3..=6 } else { 6..=6 };
+    if !allowed_field_counts.contains(&fields.len()) {
+        return Err(FENParseError::IncorrectPartsCount(fields.len()));
+    }
 
-        let castling_rights = if fen_parts[2].chars().next() == Some('-') {
-            0
-        } else {
-            fen_parts[2].chars().try_fold(0, |acc, c| match c {
-                'K' => Ok(acc | CastleMoves::WhiteKingsideCastle as u8),
-                'Q' => Ok(acc | CastleMoves::WhiteQueensideCastle as u8),
-                'k' => Ok(acc | CastleMoves::BlackKingsideCastle as u8),
-                'q' => Ok(acc | CastleMoves::BlackQueensideCastle as u8),
-                _ => Err(FENParseError::IncorrectCastlingRights),
-            })?
-        };
+    let (board_offset, board) = &fields[0];
+    let ranks: Vec<&str> = board.split('/').collect();
+    if ranks.len() != 8 {
+        return Err(FENParseError::IncorrectBoardLength {
+            field: 0,
+            offset: *board_offset,
+            ranks: ranks.len(),
+        });
+    }
 
-        let en_passant_square = match fen_parts[3].to_ascii_uppercase().as_str() {
-            "-" => None,
-            other => Some(
-                other
-                    .parse::<Square>()
-                    .map_err(|_| FENParseError::IncorrectEnPassantSquare)?,
-            ),
+    let mut pieces = [BoardSlice(0); 12];
+    let mut offset = *board_offset;
+    for (rank_index, rank) in ranks.iter().enumerate() {
+        let rank_number = 8 - rank_index;
+        let row_length_error = |offset| FENParseError::IncorrectBoardRowLength {
+            field: 0,
+            offset,
+            rank: rank_number,
         };
 
-        let half_move_clock = fen_parts[4]
-            .parse::<usize>()
-            .map_err(|_| FENParseError::IncorrectHalfMoveClock)?;
-
-        let full_move_clock = fen_parts[5]
-            .parse::<usize>()
-            .map_err(|_| FENParseError::IncorrectFullMoveClock)?;
-
-        Ok(Bitboard {
-            pieces,
-            to_move,
-            castling_rights,
-            en_passant_square,
-            half_move_clock,
-            full_move_clock,
-        })
+        let mut file = 0;
+        for c in rank.chars() {
+            if file >= 8 {
+                return Err(row_length_error(offset));
+            }
+            match c {
+                '1'..='9' => file += c.to_digit(10).unwrap() as usize,
+                _ => {
+                    let (color, piece) = get_fen_piece(c).ok_or(FENParseError::IncorrectBoard {
+                        field: 0,
+                        offset,
+                        symbol: c,
+                    })?;
+                    pieces[bitboard_piece_index!(color, piece)].0 |=
+                        1 << ((rank_number - 1) * 8 + file);
+                    file += 1;
+                }
+            }
+            if file > 8 {
+                return Err(row_length_error(offset));
+            }
+            offset += 1;
+        }
+        if file != 8 {
+            return Err(row_length_error(offset));
+        }
+        // Skip the '/' separating ranks
+        offset += 1;
+    }
+
+    let (to_move_offset, to_move_str) = &fields[1];
+    let to_move = match to_move_str.as_str() {
+        "w" => Color::White,
+        "b" => Color::Black,
+        _ => {
+            return Err(FENParseError::IncorrectToMove {
+                field: 1,
+                offset: *to_move_offset,
+                found: to_move_str.clone(),
+            })
+        }
+    };
+
+    // Castling rights are either `KQkq`, meaning the outermost rook on that side of the king as in
+    // X-FEN, or the file of the rook as in Shredder-FEN. A king that is not on its back rank is
+    // taken to be on the e-file so the rights can still be parsed and rejected by validation.
+    let (castling_offset, castling_str) = &fields[2];
+    let mut castling_rights = 0;
+    let mut castling_rook_squares = STANDARD_CASTLING_ROOK_SQUARES;
+    let mut chess960 = false;
+    if castling_str != "-" {
+        for (index, c) in castling_str.chars().enumerate() {
+            let color = if c.is_ascii_uppercase() {
+                Color::White
+            } else {
+                Color::Black
+            };
+            let rank_start = match color {
+                Color::White => 0,
+                Color::Black => 56,
+            };
+            let rooks = (pieces[bitboard_piece_index!(color, Piece::Rook)].0 >> rank_start) & 0xFF;
+            let king_file =
+                match (pieces[bitboard_piece_index!(color, Piece::King)].0 >> rank_start) & 0xFF {
+                    0 => File::EFile as u8,
+                    kings => kings.trailing_zeros() as u8,
+                };
+
+            let castle = match c.to_ascii_lowercase() {
+                'k' => Some((
+                    true,
+                    (king_file + 1..8)
+                        .rev()
+                        .find(|&file| rooks & (1 << file) != 0)
+                        .unwrap_or(File::HFile as u8),
+                )),
+                'q' => Some((
+                    false,
+                    (0..king_file)
+                        .find(|&file| rooks & (1 << file) != 0)
+                        .unwrap_or(File::AFile as u8),
+                )),
+                symbol @ 'a'..='h' if symbol as u8 - b'a' != king_file => {
+                    chess960 = true;
+                    let rook_file = symbol as u8 - b'a';
+                    Some((rook_file > king_file, rook_file))
+                }
+                _ => None,
+            };
+            match castle.map(|(kingside, rook_file)| (CastleMoves::new(color, kingside), rook_file))
+            {
+                Some((cm, rook_file)) if castling_rights & cm as u8 == 0 => {
+                    castling_rights |= cm as u8;
+                    castling_rook_squares[cm.index()] =
+                        Square::from_int(rank_start as u8 + rook_file).unwrap();
+                    chess960 |= castling_rook_squares[cm.index()]
+                        != STANDARD_CASTLING_ROOK_SQUARES[cm.index()]
+                        || king_file != File::EFile as u8;
+                }
+                _ => {
+                    return Err(FENParseError::IncorrectCastlingRights {
+                        field: 2,
+                        offset: castling_offset + index,
+                        symbol: c,
+                    })
+                }
+            }
+        }
     }
+
+    let en_passant_square = match fields.get(3) {
+        None => None,
+        Some((_, en_passant_str)) if en_passant_str == "-" => None,
+        Some((en_passant_offset, en_passant_str)) => Some(
+            en_passant_str
+                .to_ascii_uppercase()
+                .parse::<Square>()
+                .map_err(|_| FENParseError::IncorrectEnPassantSquare {
+                    field: 3,
+                    offset: *en_passant_offset,
+                    found: en_passant_str.clone(),
+                })?,
+        ),
+    };
+
+    let half_move_clock = match fields.get(4) {
+        None => 0,
+        Some((clock_offset, clock_str)) => {
+            clock_str
+                .parse::<usize>()
+                .map_err(|_| FENParseError::IncorrectHalfMoveClock {
+                    field: 4,
+                    offset: *clock_offset,
+                    found: clock_str.clone(),
+                })?
+        }
+    };
+
+    let full_move_clock = match fields.get(5) {
+        None => 1,
+        Some((clock_offset, clock_str)) => {
+            clock_str
+                .parse::<usize>()
+                .map_err(|_| FENParseError::IncorrectFullMoveClock {
+                    field: 5,
+                    offset: *clock_offset,
+                    found: clock_str.clone(),
+                })?
+        }
+    };
+
+    let mut bitboard = Bitboard {
+        pieces,
+        to_move,
+        castling_rights,
+        castling_rook_squares,
+        chess960,
+        en_passant_square,
+        half_move_clock,
+        full_move_clock,
+        zobrist_key: 0,
+    };
+    bitboard.zobrist_key = bitboard.compute_board_key();
+    Ok(bitboard)
 }
 
 impl fmt::Display for Bitboard {
@@ -445,22 +954,22 @@ pub mod tests {
         let position_fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
         let bitboard = position_fen.parse::<Bitboard>().unwrap();
 
-        assert_eq!(bitboard.is_square_attacked(Color::White, Square::E3), true);
-        assert_eq!(bitboard.is_square_attacked(Color::White, Square::E4), false);
+        assert!(bitboard.is_square_attacked(Color::White, Square::E3));
+        assert!(!bitboard.is_square_attacked(Color::White, Square::E4));
 
-        assert_eq!(bitboard.is_square_attacked(Color::Black, Square::E6), true);
-        assert_eq!(bitboard.is_square_attacked(Color::Black, Square::E5), false);
+        assert!(bitboard.is_square_attacked(Color::Black, Square::E6));
+        assert!(!bitboard.is_square_attacked(Color::Black, Square::E5));
 
-        assert_eq!(bitboard.is_square_attacked(Color::White, Square::E1), true);
+        assert!(bitboard.is_square_attacked(Color::White, Square::E1));
 
         let position_fen = "k6q/8/8/8/7R/8/8/K6B w KQkq - 0 1";
-        let bitboard = position_fen.parse::<Bitboard>().unwrap();
+        let bitboard = parse_fen(position_fen, false).unwrap();
 
-        assert_eq!(bitboard.is_square_attacked(Color::White, Square::D5), true);
-        assert_eq!(bitboard.is_square_attacked(Color::White, Square::A3), false);
+        assert!(bitboard.is_square_attacked(Color::White, Square::D5));
+        assert!(!bitboard.is_square_attacked(Color::White, Square::A3));
 
-        assert_eq!(bitboard.is_square_attacked(Color::Black, Square::A7), true);
-        assert_eq!(bitboard.is_square_attacked(Color::Black, Square::H1), false);
+        assert!(bitboard.is_square_attacked(Color::Black, Square::A7));
+        assert!(!bitboard.is_square_attacked(Color::Black, Square::H1));
     }
 
     #[test]
@@ -468,13 +977,13 @@ pub mod tests {
         let position_fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
         let bitboard = position_fen.parse::<Bitboard>().unwrap();
 
-        assert_eq!(bitboard.is_king_in_check(Color::White), false);
+        assert!(!bitboard.is_king_in_check(Color::White));
 
         let position_fen = "k6q/8/8/8/7R/8/8/K6B w KQkq - 0 1";
-        let bitboard = position_fen.parse::<Bitboard>().unwrap();
+        let bitboard = parse_fen(position_fen, false).unwrap();
 
-        assert_eq!(bitboard.is_king_in_check(Color::White), true);
-        assert_eq!(bitboard.is_king_in_check(Color::Black), true);
+        assert!(bitboard.is_king_in_check(Color::White));
+        assert!(bitboard.is_king_in_check(Color::Black));
     }
 
     #[test]
@@ -527,65 +1036,69 @@ pub mod tests {
     #[test]
     fn test_from_str_valid_1() {
         let position_fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
-        assert_eq!(
-            position_fen.parse::<Bitboard>(),
-            Ok(Bitboard {
-                pieces: [
-                    BoardSlice(0x000000000000FF00),
-                    BoardSlice(0x0000000000000042),
-                    BoardSlice(0x0000000000000024),
-                    BoardSlice(0x0000000000000081),
-                    BoardSlice(0x0000000000000008),
-                    BoardSlice(0x0000000000000010),
-                    BoardSlice(0x00FF000000000000),
-                    BoardSlice(0x4200000000000000),
-                    BoardSlice(0x2400000000000000),
-                    BoardSlice(0x8100000000000000),
-                    BoardSlice(0x0800000000000000),
-                    BoardSlice(0x1000000000000000),
-                ],
-                to_move: Color::White,
-                castling_rights: (CastleMoves::WhiteKingsideCastle as u8
-                    | CastleMoves::WhiteQueensideCastle as u8
-                    | CastleMoves::BlackKingsideCastle as u8
-                    | CastleMoves::BlackQueensideCastle as u8),
-                en_passant_square: None,
-                half_move_clock: 0,
-                full_move_clock: 1
-            })
-        );
+        let mut expected = Bitboard {
+            pieces: [
+                BoardSlice(0x000000000000FF00),
+                BoardSlice(0x0000000000000042),
+                BoardSlice(0x0000000000000024),
+                BoardSlice(0x0000000000000081),
+                BoardSlice(0x0000000000000008),
+                BoardSlice(0x0000000000000010),
+                BoardSlice(0x00FF000000000000),
+                BoardSlice(0x4200000000000000),
+                BoardSlice(0x2400000000000000),
+                BoardSlice(0x8100000000000000),
+                BoardSlice(0x0800000000000000),
+                BoardSlice(0x1000000000000000),
+            ],
+            to_move: Color::White,
+            castling_rights: (CastleMoves::WhiteKingsideCastle as u8
+                | CastleMoves::WhiteQueensideCastle as u8
+                | CastleMoves::BlackKingsideCastle as u8
+                | CastleMoves::BlackQueensideCastle as u8),
+            castling_rook_squares: STANDARD_CASTLING_ROOK_SQUARES,
+            chess960: false,
+            en_passant_square: None,
+            half_move_clock: 0,
+            full_move_clock: 1,
+            zobrist_key: 0,
+        };
+        expected.zobrist_key = expected.compute_board_key();
+        assert_eq!(position_fen.parse::<Bitboard>(), Ok(expected));
     }
 
     #[test]
     fn test_from_str_valid_2() {
         let position_fen = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 1 1";
-        assert_eq!(
-            position_fen.parse::<Bitboard>(),
-            Ok(Bitboard {
-                pieces: [
-                    BoardSlice(0x000000001000EF00),
-                    BoardSlice(0x0000000000000042),
-                    BoardSlice(0x0000000000000024),
-                    BoardSlice(0x0000000000000081),
-                    BoardSlice(0x0000000000000008),
-                    BoardSlice(0x0000000000000010),
-                    BoardSlice(0x00FF000000000000),
-                    BoardSlice(0x4200000000000000),
-                    BoardSlice(0x2400000000000000),
-                    BoardSlice(0x8100000000000000),
-                    BoardSlice(0x0800000000000000),
-                    BoardSlice(0x1000000000000000),
-                ],
-                to_move: Color::Black,
-                castling_rights: (CastleMoves::WhiteKingsideCastle as u8
-                    | CastleMoves::WhiteQueensideCastle as u8
-                    | CastleMoves::BlackKingsideCastle as u8
-                    | CastleMoves::BlackQueensideCastle as u8),
-                en_passant_square: Some(Square::E3),
-                half_move_clock: 1,
-                full_move_clock: 1
-            })
-        )
+        let mut expected = Bitboard {
+            pieces: [
+                BoardSlice(0x000000001000EF00),
+                BoardSlice(0x0000000000000042),
+                BoardSlice(0x0000000000000024),
+                BoardSlice(0x0000000000000081),
+                BoardSlice(0x0000000000000008),
+                BoardSlice(0x0000000000000010),
+                BoardSlice(0x00FF000000000000),
+                BoardSlice(0x4200000000000000),
+                BoardSlice(0x2400000000000000),
+                BoardSlice(0x8100000000000000),
+                BoardSlice(0x0800000000000000),
+                BoardSlice(0x1000000000000000),
+            ],
+            to_move: Color::Black,
+            castling_rights: (CastleMoves::WhiteKingsideCastle as u8
+                | CastleMoves::WhiteQueensideCastle as u8
+                | CastleMoves::BlackKingsideCastle as u8
+                | CastleMoves::BlackQueensideCastle as u8),
+            castling_rook_squares: STANDARD_CASTLING_ROOK_SQUARES,
+            chess960: false,
+            en_passant_square: Some(Square::E3),
+            half_move_clock: 1,
+            full_move_clock: 1,
+            zobrist_key: 0,
+        };
+        expected.zobrist_key = expected.compute_board_key();
+        assert_eq!(position_fen.parse::<Bitboard>(), Ok(expected))
     }
 
     #[test]
@@ -599,49 +1112,435 @@ pub mod tests {
         let position_fen = "rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
         assert_eq!(
             position_fen.parse::<Bitboard>(),
-            Err(FENParseError::IncorrectBoardRowLength(6))
+            Err(FENParseError::IncorrectBoardRowLength {
+                field: 0,
+                offset: 18,
+                rank: 6,
+            })
         );
 
         let position_fen = "rnbqkbnrr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
         assert_eq!(
             position_fen.parse::<Bitboard>(),
-            Err(FENParseError::IncorrectBoardRowLength(8))
+            Err(FENParseError::IncorrectBoardRowLength {
+                field: 0,
+                offset: 8,
+                rank: 8,
+            })
         );
 
         let position_fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNt w KQkq - 0 1";
         assert_eq!(
             position_fen.parse::<Bitboard>(),
-            Err(FENParseError::IncorrectBoard('t'))
+            Err(FENParseError::IncorrectBoard {
+                field: 0,
+                offset: 42,
+                symbol: 't',
+            })
         );
 
         let position_fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1";
         assert_eq!(
             position_fen.parse::<Bitboard>(),
-            Err(FENParseError::IncorrectToMove)
+            Err(FENParseError::IncorrectToMove {
+                field: 1,
+                offset: 44,
+                found: String::from("x"),
+            })
         );
 
         let position_fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w xKQkq - 0 1";
         assert_eq!(
             position_fen.parse::<Bitboard>(),
-            Err(FENParseError::IncorrectCastlingRights)
+            Err(FENParseError::IncorrectCastlingRights {
+                field: 2,
+                offset: 46,
+                symbol: 'x',
+            })
         );
 
         let position_fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e9 0 1";
         assert_eq!(
             position_fen.parse::<Bitboard>(),
-            Err(FENParseError::IncorrectEnPassantSquare)
+            Err(FENParseError::IncorrectEnPassantSquare {
+                field: 3,
+                offset: 51,
+                found: String::from("e9"),
+            })
         );
 
         let position_fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - x 1";
         assert_eq!(
             position_fen.parse::<Bitboard>(),
-            Err(FENParseError::IncorrectHalfMoveClock)
+            Err(FENParseError::IncorrectHalfMoveClock {
+                field: 4,
+                offset: 53,
+                found: String::from("x"),
+            })
         );
 
         let position_fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 x";
         assert_eq!(
             position_fen.parse::<Bitboard>(),
-            Err(FENParseError::IncorrectFullMoveClock)
+            Err(FENParseError::IncorrectFullMoveClock {
+                field: 5,
+                offset: 55,
+                found: String::from("x"),
+            })
+        );
+
+        let position_fen = "rnbqkbnr/pppppppp/7/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(
+            position_fen.parse::<Bitboard>(),
+            Err(FENParseError::IncorrectBoardRowLength {
+                field: 0,
+                offset: 19,
+                rank: 6,
+            })
+        );
+
+        let position_fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkK - 0 1";
+        assert_eq!(
+            position_fen.parse::<Bitboard>(),
+            Err(FENParseError::IncorrectCastlingRights {
+                field: 2,
+                offset: 49,
+                symbol: 'K',
+            })
+        );
+
+        // Runs of whitespace and leading or trailing whitespace are accepted
+        let start_fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        for position_fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -  0 1",
+            "  rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR\tw KQkq - 0 1\n",
+        ] {
+            assert_eq!(
+                position_fen.parse::<Bitboard>(),
+                start_fen.parse::<Bitboard>()
+            );
+        }
+
+        assert_eq!(
+            FENParseError::IncorrectEnPassantSquare {
+                field: 3,
+                offset: 51,
+                found: String::from("e9"),
+            }
+            .to_string(),
+            "Incorrect en passant square e9 (field 3, offset 51)."
+        );
+    }
+
+    #[test]
+    fn test_zobrist_key() {
+        let start_fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        let mut bitboard = start_fen.parse::<Bitboard>().unwrap();
+        let start_key = bitboard.get_zobrist_key();
+        assert_eq!(start_key, bitboard.compute_zobrist_key());
+
+        // Same position reached with different clocks has the same key.
+        bitboard.move_piece(Color::White, Piece::Knight, Square::G1, Square::F3);
+        bitboard.toggle_move();
+        assert_ne!(bitboard.get_zobrist_key(), start_key);
+        bitboard.move_piece(Color::Black, Piece::Knight, Square::G8, Square::F6);
+        bitboard.toggle_move();
+        bitboard.move_piece(Color::White, Piece::Knight, Square::F3, Square::G1);
+        bitboard.toggle_move();
+        bitboard.move_piece(Color::Black, Piece::Knight, Square::F6, Square::G8);
+        bitboard.toggle_move();
+        assert_eq!(bitboard.get_zobrist_key(), start_key);
+
+        bitboard.remove_castling_right(CastleMoves::BlackQueensideCastle);
+        assert_eq!(bitboard.get_zobrist_key(), bitboard.compute_zobrist_key());
+        assert_eq!(
+            bitboard.get_zobrist_key(),
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQk - 4 3"
+                .parse::<Bitboard>()
+                .unwrap()
+                .get_zobrist_key()
+        );
+
+        bitboard.set_en_passant_square(Some(Square::D6));
+        assert_eq!(bitboard.get_zobrist_key(), bitboard.compute_zobrist_key());
+        bitboard.set_en_passant_square(None);
+        assert_eq!(bitboard.get_zobrist_key(), bitboard.compute_zobrist_key());
+
+        // The en passant file only counts when a pawn can capture on it
+        let key = |fen: &str| fen.parse::<Bitboard>().unwrap().get_zobrist_key();
+        assert_eq!(
+            key("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"),
+            key("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1")
+        );
+        assert_ne!(
+            key("rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 3"),
+            key("rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 3")
+        );
+    }
+
+    #[test]
+    fn test_parse_uci_move() {
+        let position_fen = "r3k3/1P6/8/8/8/8/8/4K2R w K - 0 1";
+        let bitboard = position_fen.parse::<Bitboard>().unwrap();
+
+        assert_eq!(
+            bitboard.parse_uci_move("b7a8q"),
+            Ok(Move {
+                orig: Square::B7,
+                dest: Square::A8,
+                promotion: Some(Piece::Queen),
+            })
+        );
+        assert_eq!(
+            bitboard.parse_uci_move("e1g1"),
+            Ok(Move {
+                orig: Square::E1,
+                dest: Square::G1,
+                promotion: None,
+            })
+        );
+        assert_eq!(
+            bitboard.parse_uci_move("b7b8"),
+            Err(MoveParseError::IllegalMove(Move {
+                orig: Square::B7,
+                dest: Square::B8,
+                promotion: None,
+            }))
+        );
+        assert_eq!(
+            bitboard.parse_uci_move("e1e3"),
+            Err(MoveParseError::IllegalMove(Move {
+                orig: Square::E1,
+                dest: Square::E3,
+                promotion: None,
+            }))
+        );
+        assert_eq!(
+            bitboard.parse_uci_move("e1"),
+            Err(MoveParseError::IncorrectLength(2))
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        let valid_fens = [
+            STARTING_POSITION_FEN,
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1",
+        ];
+        for position_fen in valid_fens {
+            let bitboard = position_fen.parse::<Bitboard>().unwrap();
+            assert_eq!(bitboard.validate(), vec![], "{}", position_fen);
+        }
+
+        let bitboard = parse_fen("8/8/8/8/8/8/8/KK6 w - - 0 1", false).unwrap();
+        assert_eq!(
+            bitboard.validate(),
+            vec![
+                PositionError::IncorrectKingCount(Color::White, 2),
+                PositionError::IncorrectKingCount(Color::Black, 0),
+            ]
+        );
+
+        let bitboard = "P3k3/8/8/8/8/8/8/4K2p w KQ e6 0 1"
+            .parse::<Bitboard>()
+            .unwrap();
+        assert_eq!(
+            bitboard.validate(),
+            vec![
+                PositionError::PawnOnBackRank(Square::H1),
+                PositionError::PawnOnBackRank(Square::A8),
+                PositionError::InvalidEnPassantSquare(Square::E6),
+                PositionError::InvalidCastlingRights(CastleMoves::WhiteKingsideCastle),
+                PositionError::InvalidCastlingRights(CastleMoves::WhiteQueensideCastle),
+            ]
+        );
+
+        let bitboard = "4k3/8/8/8/8/8/8/4K2R b K - 0 1"
+            .parse::<Bitboard>()
+            .unwrap();
+        assert_eq!(bitboard.validate(), vec![]);
+        let bitboard = parse_fen("4k3/8/8/8/8/8/8/4R2K w - - 0 1", false).unwrap();
+        assert_eq!(bitboard.validate(), vec![PositionError::OpponentInCheck]);
+
+        let mut bitboard = "4k3/8/8/8/8/8/8/4K3 w - - 0 1".parse::<Bitboard>().unwrap();
+        bitboard.add_piece(Color::White, Piece::Queen, Square::E8);
+        assert_eq!(
+            bitboard.validate(),
+            vec![PositionError::OverlappingPieces(Square::E8)]
+        );
+    }
+
+    #[test]
+    fn test_from_fen() {
+        let kingless_fen = "8/8/8/8/8/8/8/8 w - - 0 1";
+        for mode in [
+            FENParseMode::Standard,
+            FENParseMode::Strict,
+            FENParseMode::Lenient,
+        ] {
+            assert_eq!(
+                Bitboard::from_fen(kingless_fen, mode),
+                Err(FENParseError::InvalidPosition(vec![
+                    PositionError::IncorrectKingCount(Color::White, 0),
+                    PositionError::IncorrectKingCount(Color::Black, 0),
+                ]))
+            );
+        }
+        assert_eq!(
+            "4k3/8/8/8/8/8/8/4R2K w - - 0 1".parse::<Bitboard>(),
+            Err(FENParseError::InvalidPosition(vec![
+                PositionError::OpponentInCheck
+            ]))
+        );
+        // Other problems only fail strict parsing
+        let castling_fen = "4k3/8/8/8/8/8/8/4K3 w K - 0 1";
+        assert!(castling_fen.parse::<Bitboard>().is_ok());
+        assert_eq!(
+            Bitboard::from_fen(castling_fen, FENParseMode::Strict),
+            Err(FENParseError::InvalidPosition(vec![
+                PositionError::InvalidCastlingRights(CastleMoves::WhiteKingsideCastle)
+            ]))
+        );
+        assert_eq!(
+            Bitboard::from_fen(STARTING_POSITION_FEN, FENParseMode::Strict),
+            STARTING_POSITION_FEN.parse::<Bitboard>()
+        );
+        assert_eq!(
+            Bitboard::from_fen("8/8/8 w - - 0 1", FENParseMode::Strict),
+            Err(FENParseError::IncorrectBoardLength {
+                field: 0,
+                offset: 0,
+                ranks: 3,
+            })
+        );
+    }
+
+    #[test]
+    fn test_chess960_fen() {
+        // Standard positions stay in standard mode unless Shredder-FEN names the rooks.
+        let bitboard = STARTING_POSITION_FEN.parse::<Bitboard>().unwrap();
+        assert!(!bitboard.chess960);
+        assert_eq!(bitboard.to_shredder_fen(), STARTING_POSITION_FEN);
+        let bitboard = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w HAha - 0 1"
+            .parse::<Bitboard>()
+            .unwrap();
+        assert!(bitboard.chess960);
+        assert_eq!(bitboard.to_str(), STARTING_POSITION_FEN);
+        assert_eq!(
+            bitboard.to_shredder_fen(),
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w HAha - 0 1"
+        );
+
+        let shredder_fen = "1r2k1r1/8/8/8/8/8/8/1R2K1R1 w GBgb - 0 1";
+        let x_fen = "1r2k1r1/8/8/8/8/8/8/1R2K1R1 w KQkq - 0 1";
+        let bitboard = shredder_fen.parse::<Bitboard>().unwrap();
+        assert!(bitboard.chess960);
+        assert_eq!(x_fen.parse::<Bitboard>(), Ok(bitboard));
+        assert_eq!(
+            bitboard.get_castling_rook_square(CastleMoves::WhiteKingsideCastle),
+            Square::G1
+        );
+        assert_eq!(
+            bitboard.get_castling_rook_square(CastleMoves::BlackQueensideCastle),
+            Square::B8
+        );
+        assert_eq!(bitboard.to_str(), x_fen);
+        assert_eq!(bitboard.to_shredder_fen(), shredder_fen);
+
+        // X-FEN names the file of a rook that is not the outermost one.
+        let bitboard = "4k3/8/8/8/8/8/8/1RK3RR w G - 0 1"
+            .parse::<Bitboard>()
+            .unwrap();
+        assert_eq!(
+            bitboard.get_castling_rook_square(CastleMoves::WhiteKingsideCastle),
+            Square::G1
+        );
+        assert_eq!(bitboard.to_str(), "4k3/8/8/8/8/8/8/1RK3RR w G - 0 1");
+        let bitboard = "4k3/8/8/8/8/8/8/1RK3RR w K - 0 1"
+            .parse::<Bitboard>()
+            .unwrap();
+        assert_eq!(
+            bitboard.get_castling_rook_square(CastleMoves::WhiteKingsideCastle),
+            Square::H1
+        );
+
+        assert_eq!(
+            "4k3/8/8/8/8/8/8/2K3R1 w C - 0 1".parse::<Bitboard>(),
+            Err(FENParseError::IncorrectCastlingRights {
+                field: 2,
+                offset: 24,
+                symbol: 'C',
+            })
+        );
+    }
+
+    #[test]
+    fn test_from_chess960_index() {
+        assert_eq!(
+            Bitboard::from_chess960_index(0).unwrap().to_str(),
+            "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1"
+        );
+        let standard = Bitboard::from_chess960_index(518).unwrap();
+        assert!(standard.chess960);
+        assert_eq!(standard.to_str(), STARTING_POSITION_FEN);
+        assert_eq!(
+            Bitboard::from_chess960_index(959).unwrap().to_str(),
+            "rkrnnqbb/pppppppp/8/8/8/8/PPPPPPPP/RKRNNQBB w KQkq - 0 1"
+        );
+        assert_eq!(Bitboard::from_chess960_index(960), None);
+
+        let mut positions = std::collections::HashSet::new();
+        for index in 0..960 {
+            let bitboard = Bitboard::from_chess960_index(index).unwrap();
+            assert_eq!(bitboard.validate(), vec![], "{}", index);
+            positions.insert(bitboard.to_str());
+        }
+        assert_eq!(positions.len(), 960);
+    }
+
+    #[test]
+    fn test_from_fen_lenient() {
+        let expected = STARTING_POSITION_FEN.parse::<Bitboard>().unwrap();
+        for position_fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq",
+            "  rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR\tw  KQkq\n",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0",
+        ] {
+            assert_eq!(
+                Bitboard::from_fen(position_fen, FENParseMode::Lenient),
+                Ok(expected),
+                "{}",
+                position_fen
+            );
+            assert!(position_fen.parse::<Bitboard>().is_err());
+        }
+
+        let bitboard = Bitboard::from_fen(
+            "rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3",
+            FENParseMode::Lenient,
         )
+        .unwrap();
+        assert_eq!(bitboard.en_passant_square, Some(Square::E3));
+        assert_eq!(bitboard.half_move_clock, 0);
+        assert_eq!(bitboard.full_move_clock, 1);
+
+        assert_eq!(
+            Bitboard::from_fen(
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR  x KQkq",
+                FENParseMode::Lenient
+            ),
+            Err(FENParseError::IncorrectToMove {
+                field: 1,
+                offset: 45,
+                found: String::from("x"),
+            })
+        );
+        assert_eq!(
+            Bitboard::from_fen("8/8/8/8/8/8/8/8 w", FENParseMode::Lenient),
+            Err(FENParseError::IncorrectPartsCount(2))
+        );
     }
 }
@@ -0,0 +1,389 @@
+use crate::{
+    bitboard::Bitboard,
+    move_generation::{generate_legal_moves, MoveList},
+    outcome::PositionHistory,
+    search::search,
+    transposition_table::TranspositionTable,
+    utils::{_move::Move, enums::Color},
+};
+
+use std::{
+    io::Write,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+/// Time kept back from every move so that the engine does not lose on time to communication
+/// delays.
+const MOVE_OVERHEAD: Duration = Duration::from_millis(30);
+/// Number of moves the remaining time is spread over when the time control does not say.
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+
+/// Where a protocol front end writes to the GUI. The search thread writes to it too.
+pub type Output = Arc<Mutex<dyn Write + Send>>;
+
+/// Write `line` to `output` and flush it, so that the GUI sees it at once. Write errors are
+/// ignored: a GUI that went away sends no more commands either.
+pub fn send(output: &Output, line: &str) {
+    let mut output = output.lock().unwrap();
+    let _ = writeln!(output, "{}", line);
+    let _ = output.flush();
+}
+
+/// When a search has to stop. Limits left at `None` do not apply; a search without any limit
+/// runs until it is stopped.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct SearchLimits {
+    pub depth: Option<usize>,
+    pub nodes: Option<u64>,
+    pub move_time: Option<Duration>,
+    pub white_time: Option<Duration>,
+    pub black_time: Option<Duration>,
+    pub white_increment: Duration,
+    pub black_increment: Duration,
+    pub moves_to_go: Option<u32>,
+    /// Keep the best move to ourselves until stopped, even when the search ends early.
+    pub infinite: bool,
+    /// Search while the opponent thinks: like `infinite` until `Engine::ponderhit`, after which
+    /// the other limits apply, the clock starting then.
+    pub ponder: bool,
+}
+
+impl SearchLimits {
+    /// How long `color` may think about its move: the fixed move time if there is one, otherwise
+    /// an even share of the clock plus most of the increment.
+    pub fn time_budget(&self, color: Color) -> Option<Duration> {
+        if self.move_time.is_some() {
+            return self.move_time;
+        }
+
+        let (time, increment) = match color {
+            Color::White => (self.white_time?, self.white_increment),
+            Color::Black => (self.black_time?, self.black_increment),
+        };
+        let moves_to_go = self.moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+        let budget = time / moves_to_go + increment * 3 / 4;
+        Some(budget.min(time.saturating_sub(MOVE_OVERHEAD)))
+    }
+}
+
+/// Progress of a search, reported after each completed iteration.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SearchInfo {
+    pub depth: usize,
+    /// Centipawns from the point of view of the side to move, or a mate score.
+    pub score: i32,
+    pub nodes: u64,
+    pub elapsed: Duration,
+    pub pv: Vec<Move>,
+    /// Permille of the transposition table in use.
+    pub hashfull: u16,
+}
+
+impl SearchInfo {
+    /// Nodes searched per second.
+    pub fn nps(&self) -> u64 {
+        (self.nodes as u128 * 1000 / self.elapsed.as_millis().max(1)) as u64
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum SearchEvent {
+    Info(SearchInfo),
+    /// The search is over; `None` when there is no legal move.
+    BestMove(Option<Move>),
+}
+
+/// Runs searches on a background thread so that the protocol front ends can keep reading
+/// commands, and stop the search, while it thinks.
+#[derive(Debug, Default)]
+pub struct Engine {
+    stop: Arc<AtomicBool>,
+    /// Set while a search started with `SearchLimits::ponder` waits for `ponderhit`.
+    pondering: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+    /// Locked by the search thread for as long as it runs.
+    tt: Arc<Mutex<TranspositionTable>>,
+}
+
+impl Engine {
+    pub fn new() -> Engine {
+        Engine::default()
+    }
+
+    /// Start searching `bitboard`, reached through the positions of `history`. `report` is called
+    /// from the search thread with each `SearchEvent`, the last one always being
+    /// `SearchEvent::BestMove`. A search that is still running is stopped first.
+    pub fn start<F>(
+        &mut self,
+        bitboard: Bitboard,
+        history: PositionHistory,
+        limits: SearchLimits,
+        mut report: F,
+    ) where
+        F: FnMut(SearchEvent) + Send + 'static,
+    {
+        self.stop();
+        self.stop.store(false, Ordering::SeqCst);
+        self.pondering.store(limits.ponder, Ordering::SeqCst);
+
+        let stop = Arc::clone(&self.stop);
+        let pondering = Arc::clone(&self.pondering);
+        let tt = Arc::clone(&self.tt);
+        self.thread = Some(thread::spawn(move || {
+            let mut tt = tt.lock().unwrap_or_else(PoisonError::into_inner);
+            let mut reported_move = None;
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                search(
+                    &bitboard,
+                    &history,
+                    &limits,
+                    &mut tt,
+                    &stop,
+                    &pondering,
+                    &mut |info| {
+                        reported_move = info.pv.first().copied();
+                        report(SearchEvent::Info(info.clone()))
+                    },
+                )
+            }));
+            let best_move = match result {
+                Ok(result) => result.best_move,
+                Err(_) => reported_move.or_else(|| fallback_move(&bitboard)),
+            };
+            while (limits.infinite || pondering.load(Ordering::SeqCst))
+                && !stop.load(Ordering::SeqCst)
+            {
+                thread::sleep(Duration::from_millis(1));
+            }
+            report(SearchEvent::BestMove(best_move));
+        }));
+    }
+
+    /// The opponent played the move the running search ponders on: start its clock, or report
+    /// its best move if it is already done. Does nothing when not pondering.
+    pub fn ponderhit(&self) {
+        self.pondering.store(false, Ordering::SeqCst);
+    }
+
+    /// Stop the running search, if any, and wait for it to report its best move.
+    pub fn stop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        self.wait();
+    }
+
+    /// Wait for the running search, if any, to finish by itself. A search that panicked still
+    /// reports a best move and leaves the engine able to start the next one.
+    pub fn wait(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+
+    /// Stop the running search, if any, and resize the transposition table to about `size_mb`
+    /// megabytes, emptying it.
+    pub fn set_hash_size(&mut self, size_mb: usize) {
+        self.stop();
+        self.tt
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .resize(size_mb);
+    }
+
+    /// Stop the running search, if any, and forget everything it and earlier searches found.
+    pub fn clear_hash(&mut self) {
+        self.stop();
+        self.tt
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+    }
+
+    pub fn is_searching(&self) -> bool {
+        self.thread
+            .as_ref()
+            .is_some_and(|thread| !thread.is_finished())
+    }
+}
+
+/// The move to play when the search panicked: the first legal move, if even that can be found.
+fn fallback_move(bitboard: &Bitboard) -> Option<Move> {
+    panic::catch_unwind(|| {
+        let mut moves = MoveList::new();
+        generate_legal_moves(bitboard, &mut moves);
+        moves.first().copied()
+    })
+    .ok()
+    .flatten()
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::{
+        bitboard::STARTING_POSITION_FEN,
+        evaluation::MATE_SCORE,
+        utils::enums::{Piece, Square},
+    };
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_time_budget() {
+        let limits = SearchLimits {
+            white_time: Some(Duration::from_secs(60)),
+            black_time: Some(Duration::from_secs(30)),
+            white_increment: Duration::from_secs(2),
+            ..Default::default()
+        };
+        assert_eq!(
+            limits.time_budget(Color::White),
+            Some(Duration::from_millis(3500))
+        );
+        assert_eq!(
+            limits.time_budget(Color::Black),
+            Some(Duration::from_secs(1))
+        );
+
+        // Never more than is left on the clock
+        let limits = SearchLimits {
+            white_time: Some(Duration::from_millis(100)),
+            white_increment: Duration::from_secs(1),
+            moves_to_go: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(
+            limits.time_budget(Color::White),
+            Some(Duration::from_millis(70))
+        );
+
+        let limits = SearchLimits {
+            move_time: Some(Duration::from_millis(250)),
+            ..Default::default()
+        };
+        assert_eq!(
+            limits.time_budget(Color::Black),
+            Some(Duration::from_millis(250))
+        );
+        assert_eq!(SearchLimits::default().time_budget(Color::White), None);
+    }
+
+    fn run_search(fen: &str, limits: SearchLimits) -> Vec<SearchEvent> {
+        let bitboard = fen.parse::<Bitboard>().unwrap();
+        let (sender, receiver) = mpsc::channel();
+        let mut engine = Engine::new();
+        engine.start(
+            bitboard,
+            PositionHistory::new(&bitboard),
+            limits,
+            move |event| sender.send(event).unwrap(),
+        );
+        engine.wait();
+        receiver.try_iter().collect()
+    }
+
+    #[test]
+    fn test_engine() {
+        let events = run_search(
+            STARTING_POSITION_FEN,
+            SearchLimits {
+                depth: Some(1),
+                ..Default::default()
+            },
+        );
+        assert!(matches!(
+            events.last(),
+            Some(SearchEvent::BestMove(Some(_)))
+        ));
+
+        // Mate in one
+        let events = run_search("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", SearchLimits::default());
+        let mate = "a1a8".parse::<Move>().unwrap();
+        assert!(events.iter().any(|event| matches!(
+            event,
+            SearchEvent::Info(info) if info.score == MATE_SCORE - 1 && info.pv == vec![mate]
+        )));
+        assert_eq!(events.last(), Some(&SearchEvent::BestMove(Some(mate))));
+
+        // Checkmated
+        let events = run_search("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1", SearchLimits::default());
+        assert_eq!(events, vec![SearchEvent::BestMove(None)]);
+    }
+
+    #[test]
+    fn test_stop_infinite_search() {
+        let bitboard = STARTING_POSITION_FEN.parse::<Bitboard>().unwrap();
+        let (sender, receiver) = mpsc::channel();
+        let mut engine = Engine::new();
+        engine.start(
+            bitboard,
+            PositionHistory::new(&bitboard),
+            SearchLimits {
+                infinite: true,
+                ..Default::default()
+            },
+            move |event| sender.send(event).unwrap(),
+        );
+        thread::sleep(Duration::from_millis(20));
+        assert!(engine.is_searching());
+        assert!(!receiver
+            .try_iter()
+            .any(|event| matches!(event, SearchEvent::BestMove(_))));
+
+        engine.stop();
+        assert!(!engine.is_searching());
+        assert!(matches!(
+            receiver.try_iter().last(),
+            Some(SearchEvent::BestMove(Some(_)))
+        ));
+    }
+
+    #[test]
+    fn test_search_panic() {
+        // Without a king the search panics in move generation
+        let mut bitboard = STARTING_POSITION_FEN.parse::<Bitboard>().unwrap();
+        bitboard.remove_piece(Color::White, Piece::King, Square::E1);
+        let (sender, receiver) = mpsc::channel();
+        let mut engine = Engine::new();
+        let report = move |event| sender.send(event).unwrap();
+        engine.start(
+            bitboard,
+            PositionHistory::new(&bitboard),
+            SearchLimits::default(),
+            report.clone(),
+        );
+        engine.stop();
+        assert_eq!(
+            receiver.try_iter().collect::<Vec<_>>(),
+            vec![SearchEvent::BestMove(None)]
+        );
+
+        // The engine and its transposition table are still usable
+        engine.clear_hash();
+        let bitboard = STARTING_POSITION_FEN.parse::<Bitboard>().unwrap();
+        engine.start(
+            bitboard,
+            PositionHistory::new(&bitboard),
+            SearchLimits {
+                depth: Some(1),
+                ..Default::default()
+            },
+            report,
+        );
+        engine.wait();
+        assert!(matches!(
+            receiver.try_iter().last(),
+            Some(SearchEvent::BestMove(Some(_)))
+        ));
+    }
+}
@@ -0,0 +1,365 @@
+use crate::{
+    bitboard::{Bitboard, FENParseMode},
+    utils::{
+        _move::Move,
+        errors::{EPDParseError, FENParseError},
+    },
+};
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Opcodes whose operands are SAN moves. `pv` moves are played one after another, the others
+/// are all moves from the record's position.
+const MOVE_OPCODES: [&str; 5] = ["am", "bm", "pm", "sm", "pv"];
+const INTEGER_OPCODES: [&str; 8] = ["acd", "acn", "acs", "ce", "dm", "fmvn", "hmvc", "rc"];
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum EpdOperand {
+    Move(Move),
+    Integer(i64),
+    /// A string operand, written between double quotes.
+    String(String),
+    /// Any other unquoted operand.
+    Symbol(String),
+}
+
+/// An EPD record: a position plus its operations in the order they were given.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Epd {
+    pub bitboard: Bitboard,
+    pub operations: Vec<(String, Vec<EpdOperand>)>,
+}
+
+impl Epd {
+    pub fn new(bitboard: Bitboard) -> Epd {
+        Epd {
+            bitboard,
+            operations: Vec::new(),
+        }
+    }
+
+    pub fn get(&self, opcode: &str) -> Option<&[EpdOperand]> {
+        self.operations
+            .iter()
+            .find(|(name, _)| name == opcode)
+            .map(|(_, operands)| operands.as_slice())
+    }
+
+    /// Replace the operands of `opcode`, or append the operation if it is not there yet.
+    pub fn set(&mut self, opcode: &str, operands: Vec<EpdOperand>) {
+        match self.operations.iter_mut().find(|(name, _)| name == opcode) {
+            Some((_, existing)) => *existing = operands,
+            None => self.operations.push((opcode.to_string(), operands)),
+        }
+    }
+
+    pub fn remove(&mut self, opcode: &str) {
+        self.operations.retain(|(name, _)| name != opcode);
+    }
+
+    /// Moves of a move opcode such as `bm` or `am`, empty when it is missing.
+    pub fn get_moves(&self, opcode: &str) -> Vec<Move> {
+        self.get(opcode)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|operand| match operand {
+                EpdOperand::Move(_move) => Some(*_move),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn get_integer(&self, opcode: &str) -> Option<i64> {
+        match self.get(opcode)?.first()? {
+            EpdOperand::Integer(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// First operand of `opcode` as text, e.g. the `id` or a `c0` comment.
+    pub fn get_string(&self, opcode: &str) -> Option<&str> {
+        match self.get(opcode)?.first()? {
+            EpdOperand::String(value) | EpdOperand::Symbol(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn best_moves(&self) -> Vec<Move> {
+        self.get_moves("bm")
+    }
+
+    pub fn avoid_moves(&self) -> Vec<Move> {
+        self.get_moves("am")
+    }
+
+    pub fn id(&self) -> Option<&str> {
+        self.get_string("id")
+    }
+
+    fn parse_operand(
+        &self,
+        opcode: &str,
+        token: &str,
+        quoted: bool,
+        pv_position: &mut Bitboard,
+    ) -> Result<EpdOperand, EPDParseError> {
+        if quoted {
+            return Ok(EpdOperand::String(token.to_string()));
+        }
+
+        if MOVE_OPCODES.contains(&opcode) {
+            let position = if opcode == "pv" {
+                pv_position
+            } else {
+                &mut self.bitboard.clone()
+            };
+            let _move = position
+                .parse_san(token)
+                .map_err(|error| EPDParseError::InvalidMove {
+                    opcode: opcode.to_string(),
+                    token: token.to_string(),
+                    error,
+                })?;
+            position.make_move(_move);
+            Ok(EpdOperand::Move(_move))
+        } else if INTEGER_OPCODES.contains(&opcode) {
+            token.parse::<i64>().map(EpdOperand::Integer).map_err(|_| {
+                EPDParseError::InvalidInteger {
+                    opcode: opcode.to_string(),
+                    token: token.to_string(),
+                }
+            })
+        } else {
+            Ok(EpdOperand::Symbol(token.to_string()))
+        }
+    }
+}
+
+/// Parse an EPD line: the first four FEN fields followed by `opcode operand ...;` operations.
+/// The `hmvc` and `fmvn` opcodes also set the clocks of the position.
+impl FromStr for Epd {
+    type Err = EPDParseError;
+
+    fn from_str(epd: &str) -> Result<Epd, EPDParseError> {
+        let epd = epd.trim();
+        let mut fields_end = 0;
+        for _ in 0..4 {
+            let field_start = epd[fields_end..]
+                .find(|c: char| !c.is_whitespace())
+                .map(|index| fields_end + index)
+                .ok_or_else(|| {
+                    EPDParseError::InvalidFEN(FENParseError::IncorrectPartsCount(
+                        epd.split_whitespace().count(),
+                    ))
+                })?;
+            fields_end = epd[field_start..]
+                .find(char::is_whitespace)
+                .map_or(epd.len(), |index| field_start + index);
+        }
+
+        let bitboard = Bitboard::from_fen(&epd[..fields_end], FENParseMode::Lenient)
+            .map_err(EPDParseError::InvalidFEN)?;
+        let mut record = Epd::new(bitboard);
+
+        let mut chars = epd[fields_end..].chars().peekable();
+        loop {
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+            if chars.peek().is_none() {
+                break;
+            }
+
+            let mut opcode = String::new();
+            while let Some(c) = chars.next_if(|&c| !c.is_whitespace() && c != ';') {
+                opcode.push(c);
+            }
+            if !opcode.starts_with(|c: char| c.is_ascii_alphabetic()) {
+                return Err(EPDParseError::InvalidOpcode(opcode));
+            }
+
+            let mut operands = Vec::new();
+            let mut pv_position = record.bitboard;
+            loop {
+                while chars.next_if(|c| c.is_whitespace()).is_some() {}
+                match chars.next() {
+                    None | Some(';') => break,
+                    Some('"') => {
+                        let mut token = String::new();
+                        loop {
+                            match chars.next() {
+                                Some('"') => break,
+                                Some(c) => token.push(c),
+                                None => return Err(EPDParseError::UnterminatedString(opcode)),
+                            }
+                        }
+                        operands.push(record.parse_operand(
+                            &opcode,
+                            &token,
+                            true,
+                            &mut pv_position,
+                        )?);
+                    }
+                    Some(c) => {
+                        let mut token = c.to_string();
+                        while let Some(c) = chars.next_if(|&c| !c.is_whitespace() && c != ';') {
+                            token.push(c);
+                        }
+                        operands.push(record.parse_operand(
+                            &opcode,
+                            &token,
+                            false,
+                            &mut pv_position,
+                        )?);
+                    }
+                }
+            }
+            record.operations.push((opcode, operands));
+        }
+
+        if let Some(half_move_clock) = record.get_integer("hmvc") {
+            record.bitboard.half_move_clock = half_move_clock.max(0) as usize;
+        }
+        if let Some(full_move_clock) = record.get_integer("fmvn") {
+            record.bitboard.full_move_clock = full_move_clock.max(1) as usize;
+        }
+        Ok(record)
+    }
+}
+
+impl fmt::Display for Epd {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fen = self.bitboard.to_str();
+        let fields: Vec<&str> = fen.split(' ').take(4).collect();
+        write!(f, "{}", fields.join(" "))?;
+
+        for (opcode, operands) in &self.operations {
+            write!(f, " {}", opcode)?;
+            let mut position = self.bitboard;
+            for operand in operands {
+                match operand {
+                    EpdOperand::Move(_move) => {
+                        write!(f, " {}", position.to_san(_move))?;
+                        if opcode == "pv" {
+                            position.make_move(*_move);
+                        }
+                    }
+                    EpdOperand::Integer(value) => write!(f, " {}", value)?,
+                    EpdOperand::String(value) => write!(f, " \"{}\"", value)?,
+                    EpdOperand::Symbol(value) => write!(f, " {}", value)?,
+                }
+            }
+            write!(f, ";")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_epd() {
+        let epd = "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id \"WAC.001\";"
+            .parse::<Epd>()
+            .unwrap();
+        assert_eq!(epd.best_moves(), vec!["g3g6".parse::<Move>().unwrap()]);
+        assert_eq!(epd.avoid_moves(), vec![]);
+        assert_eq!(epd.id(), Some("WAC.001"));
+        assert_eq!(
+            epd.bitboard.to_str(),
+            "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - 0 1"
+        );
+
+        let epd = "1kr5/3n4/q3p2p/p2n2p1/PppB1P2/5BP1/1P2Q2P/3R2K1 w - - bm f5; id \"Undermine.001\"; c0 \"f5=10, Be5+=2, Bf2=3, Bg4=2\";"
+            .parse::<Epd>()
+            .unwrap();
+        assert_eq!(epd.best_moves(), vec!["f4f5".parse::<Move>().unwrap()]);
+        assert_eq!(epd.get_string("c0"), Some("f5=10, Be5+=2, Bf2=3, Bg4=2"));
+
+        let epd = "4k3/8/8/8/8/8/4P3/4K3 w - - ce -35; acd 12; hmvc 7; fmvn 40; pv Kd2 Kd7 e4; bm Kd2 Kf2; foo bar baz"
+            .parse::<Epd>()
+            .unwrap();
+        assert_eq!(epd.get_integer("ce"), Some(-35));
+        assert_eq!(epd.get_integer("acd"), Some(12));
+        assert_eq!(epd.bitboard.half_move_clock, 7);
+        assert_eq!(epd.bitboard.full_move_clock, 40);
+        assert_eq!(
+            epd.get_moves("pv")
+                .iter()
+                .map(|_move| _move.to_string())
+                .collect::<Vec<String>>(),
+            ["e1d2", "e8d7", "e2e4"]
+        );
+        assert_eq!(epd.best_moves().len(), 2);
+        assert_eq!(
+            epd.get("foo"),
+            Some(
+                &[
+                    EpdOperand::Symbol(String::from("bar")),
+                    EpdOperand::Symbol(String::from("baz"))
+                ][..]
+            )
+        );
+    }
+
+    #[test]
+    fn test_write_epd() {
+        let line =
+            "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id \"WAC.001\";";
+        let epd = line.parse::<Epd>().unwrap();
+        assert_eq!(epd.to_string(), line);
+
+        let line =
+            "4k3/8/8/8/8/8/4P3/4K3 w - - acd 12; ce -35; pv Kd2 Kd7 e4; c0 \"a comment\"; noop;";
+        let epd = line.parse::<Epd>().unwrap();
+        assert_eq!(epd.to_string(), line);
+
+        let mut epd = Epd::new("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1".parse().unwrap());
+        let bitboard = epd.bitboard;
+        epd.set(
+            "bm",
+            vec![EpdOperand::Move(bitboard.parse_san("e4").unwrap())],
+        );
+        epd.set("id", vec![EpdOperand::String(String::from("pawn.1"))]);
+        epd.set("id", vec![EpdOperand::String(String::from("pawn.2"))]);
+        assert_eq!(
+            epd.to_string(),
+            "4k3/8/8/8/8/8/4P3/4K3 w - - bm e4; id \"pawn.2\";"
+        );
+        epd.remove("bm");
+        assert_eq!(
+            epd.to_string(),
+            "4k3/8/8/8/8/8/4P3/4K3 w - - id \"pawn.2\";"
+        );
+    }
+
+    #[test]
+    fn test_parse_epd_errors() {
+        assert_eq!(
+            "4k3/8/8/8/8/8/4P3/4K3 w -".parse::<Epd>(),
+            Err(EPDParseError::InvalidFEN(
+                FENParseError::IncorrectPartsCount(3)
+            ))
+        );
+        assert!(matches!(
+            "4k3/8/8/8/8/8/4P3/4K3 w - - bm Qg6;".parse::<Epd>(),
+            Err(EPDParseError::InvalidMove { opcode, token, .. }) if opcode == "bm" && token == "Qg6"
+        ));
+        assert_eq!(
+            "4k3/8/8/8/8/8/4P3/4K3 w - - acd deep;".parse::<Epd>(),
+            Err(EPDParseError::InvalidInteger {
+                opcode: String::from("acd"),
+                token: String::from("deep"),
+            })
+        );
+        assert_eq!(
+            "4k3/8/8/8/8/8/4P3/4K3 w - - id \"open;".parse::<Epd>(),
+            Err(EPDParseError::UnterminatedString(String::from("id")))
+        );
+        assert_eq!(
+            "4k3/8/8/8/8/8/4P3/4K3 w - - 12 3;".parse::<Epd>(),
+            Err(EPDParseError::InvalidOpcode(String::from("12")))
+        );
+    }
+}
@@ -0,0 +1,184 @@
+use crate::{
+    attack_tables::{get_bishop_attacks, get_rook_attacks},
+    bitboard::Bitboard,
+    utils::{
+        _move::Move,
+        board_slice::BoardSlice,
+        enums::{Color, Piece},
+    },
+};
+
+use strum::IntoEnumIterator;
+
+/// Score of mate on the board. Mate in `n` plies scores `MATE_SCORE - n`.
+pub const MATE_SCORE: i32 = 32_000;
+/// Deepest ply a search can reach, which bounds the mate distances that can occur.
+pub const MAX_PLY: usize = 128;
+
+const PIECE_VALUES: [i32; 6] = [100, 320, 330, 500, 900, 0];
+
+pub fn piece_value(piece: Piece) -> i32 {
+    PIECE_VALUES[piece as usize]
+}
+
+/// Whether `score` announces a forced mate for either side.
+pub fn is_mate_score(score: i32) -> bool {
+    score.abs() >= MATE_SCORE - MAX_PLY as i32
+}
+
+/// Static evaluation of the position in centipawns, from the point of view of the side to
+/// move. Only material is counted for now.
+pub fn evaluate(bitboard: &Bitboard) -> i32 {
+    let mut score = 0;
+    for piece in Piece::iter() {
+        let balance = bitboard.get_piece(Color::White, piece).0.count_ones() as i32
+            - bitboard.get_piece(Color::Black, piece).0.count_ones() as i32;
+        score += balance * piece_value(piece);
+    }
+
+    match bitboard.to_move {
+        Color::White => score,
+        Color::Black => -score,
+    }
+}
+
+/// Static exchange evaluation: the material `_move` wins once both sides have recaptured on its
+/// destination, each with its least valuable attacker and each free to stop when recapturing
+/// would lose material. Sliders lined up behind a capturer join in as it leaves the line.
+/// Castling wins nothing.
+pub fn see(bitboard: &Bitboard, _move: Move) -> i32 {
+    let Some((us, mut attacker)) = bitboard.get_piece_at(_move.orig) else {
+        return 0;
+    };
+    if bitboard.get_castle(&_move).is_some() {
+        return 0;
+    }
+
+    let dest_slice = BoardSlice(1 << _move.dest as u8);
+    let mut occupancy = bitboard.get_all_pieces() & !BoardSlice(1 << _move.orig as u8);
+    let mut gains = [0; 32];
+    gains[0] = match bitboard.get_piece_at(_move.dest) {
+        Some((_, captured)) => piece_value(captured),
+        None if attacker == Piece::Pawn && Some(_move.dest) == bitboard.en_passant_square => {
+            let captured_square = _move.dest as u8 ^ 8;
+            occupancy = occupancy & !BoardSlice(1 << captured_square);
+            piece_value(Piece::Pawn)
+        }
+        None => 0,
+    };
+    if let Some(promotion) = _move.promotion {
+        gains[0] += piece_value(promotion) - piece_value(Piece::Pawn);
+        attacker = promotion;
+    }
+
+    let diagonal_sliders = bitboard.get_piece(Color::White, Piece::Bishop)
+        | bitboard.get_piece(Color::Black, Piece::Bishop)
+        | bitboard.get_piece(Color::White, Piece::Queen)
+        | bitboard.get_piece(Color::Black, Piece::Queen);
+    let straight_sliders = bitboard.get_piece(Color::White, Piece::Rook)
+        | bitboard.get_piece(Color::Black, Piece::Rook)
+        | bitboard.get_piece(Color::White, Piece::Queen)
+        | bitboard.get_piece(Color::Black, Piece::Queen);
+    let mut attackers = (bitboard.get_attackers(Color::White, _move.dest, occupancy)
+        | bitboard.get_attackers(Color::Black, _move.dest, occupancy))
+        & occupancy
+        & !dest_slice;
+
+    let mut side = us.opposite();
+    let mut depth = 0;
+    loop {
+        let side_attackers = attackers & bitboard.get_color_pieces(side);
+        let Some((piece, square)) = Piece::iter().find_map(|piece| {
+            (side_attackers & bitboard.get_piece(side, piece))
+                .iter()
+                .next()
+                .map(|square| (piece, square))
+        }) else {
+            break;
+        };
+        // The king may only take last
+        let other_attackers = attackers & bitboard.get_color_pieces(side.opposite());
+        if piece == Piece::King && other_attackers.0 != 0 {
+            break;
+        }
+
+        depth += 1;
+        gains[depth] = piece_value(attacker) - gains[depth - 1];
+        attacker = piece;
+        occupancy = occupancy & !BoardSlice(1 << square as u8);
+        attackers = attackers & occupancy;
+        if matches!(piece, Piece::Pawn | Piece::Bishop | Piece::Queen) {
+            attackers = attackers | (get_bishop_attacks(_move.dest, occupancy) & diagonal_sliders);
+        }
+        if matches!(piece, Piece::Rook | Piece::Queen) {
+            attackers = attackers | (get_rook_attacks(_move.dest, occupancy) & straight_sliders);
+        }
+        attackers = attackers & occupancy;
+        side = side.opposite();
+    }
+
+    // Each side only recaptures if that does not leave it worse off than stopping
+    while depth > 0 {
+        gains[depth - 1] = -(-gains[depth - 1]).max(gains[depth]);
+        depth -= 1;
+    }
+    gains[0]
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::bitboard::STARTING_POSITION_FEN;
+
+    #[test]
+    fn test_evaluate() {
+        let bitboard = STARTING_POSITION_FEN.parse::<Bitboard>().unwrap();
+        assert_eq!(evaluate(&bitboard), 0);
+
+        // White is a knight up
+        let bitboard = "4k3/8/8/8/8/8/8/1N2K3 w - - 0 1"
+            .parse::<Bitboard>()
+            .unwrap();
+        assert_eq!(evaluate(&bitboard), 320);
+        let bitboard = "4k3/8/8/8/8/8/8/1N2K3 b - - 0 1"
+            .parse::<Bitboard>()
+            .unwrap();
+        assert_eq!(evaluate(&bitboard), -320);
+
+        assert!(is_mate_score(MATE_SCORE - 3));
+        assert!(is_mate_score(-MATE_SCORE + 10));
+        assert!(!is_mate_score(900));
+    }
+
+    #[test]
+    fn test_see() {
+        for (fen, move_str, expected) in [
+            // Free pawn
+            ("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1", "e4d5", 100),
+            // Pawn for pawn
+            ("4k3/8/2p5/3p4/4P3/8/8/4K3 w - - 0 1", "e4d5", 0),
+            // Rook for pawn
+            ("4k3/8/2p5/3p4/8/8/8/3RK3 w - - 0 1", "d1d5", -400),
+            // The second rook backs up the first through it, so Black does better not to
+            // recapture
+            ("3rk3/8/8/3n4/8/8/3R4/3R1K2 w - - 0 1", "d2d5", 320),
+            // A queen behind the rook only wins back a pawn
+            ("4k3/8/2p5/3n4/8/8/3R4/3Q1K2 w - - 0 1", "d2d5", -80),
+            // The king cannot recapture a defended piece
+            ("8/8/4k3/3p4/8/8/3R4/3RK3 w - - 0 1", "d2d5", 100),
+            ("8/8/4k3/3p4/8/8/8/3RK3 w - - 0 1", "d1d5", -400),
+            ("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6", 100),
+            ("4k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7a8q", 800),
+            ("4k3/8/8/8/8/8/8/4K3 w - - 0 1", "e1e2", 0),
+        ] {
+            let bitboard = fen.parse::<Bitboard>().unwrap();
+            assert_eq!(
+                see(&bitboard, move_str.parse().unwrap()),
+                expected,
+                "{} {}",
+                fen,
+                move_str
+            );
+        }
+    }
+}
@@ -0,0 +1,371 @@
+use crate::{
+    bitboard::Bitboard,
+    move_generation::{generate_legal_moves, MoveList},
+    pgn::{Game, GameResult, PgnMove},
+    utils::{_move::Move, errors::GameTreeError},
+};
+
+use std::fmt;
+use std::str::FromStr;
+
+pub type NodeId = usize;
+
+/// A position in a `GameTree`, reached by playing `_move` from the parent node. The first child
+/// continues the line this node is on; the other children are variations.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct GameNode {
+    _move: Option<Move>,
+    bitboard: Bitboard,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+
+    /// Only exported for the first move of a variation, before the move itself.
+    pub comment_before: Option<String>,
+    pub comment: Option<String>,
+    pub nags: Vec<u8>,
+}
+
+impl GameNode {
+    /// Move leading to this node, `None` for the root.
+    pub fn get_move(&self) -> Option<Move> {
+        self._move
+    }
+
+    pub fn get_bitboard(&self) -> &Bitboard {
+        &self.bitboard
+    }
+
+    pub fn get_parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    pub fn get_children(&self) -> &[NodeId] {
+        &self.children
+    }
+}
+
+/// A game with all its variations. Nodes live in an arena and are addressed by `NodeId`; ids of
+/// deleted nodes are never reused.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct GameTree {
+    nodes: Vec<Option<GameNode>>,
+
+    pub headers: Vec<(String, String)>,
+    pub result: GameResult,
+}
+
+impl GameTree {
+    pub const ROOT: NodeId = 0;
+
+    pub fn new(start_position: Bitboard) -> GameTree {
+        GameTree {
+            nodes: vec![Some(GameNode {
+                _move: None,
+                bitboard: start_position,
+                parent: None,
+                children: Vec::new(),
+                comment_before: None,
+                comment: None,
+                nags: Vec::new(),
+            })],
+            headers: Game::new().headers,
+            result: GameResult::Unknown,
+        }
+    }
+
+    pub fn get_node(&self, id: NodeId) -> Option<&GameNode> {
+        self.nodes.get(id).and_then(|node| node.as_ref())
+    }
+
+    pub fn get_node_mut(&mut self, id: NodeId) -> Option<&mut GameNode> {
+        self.nodes.get_mut(id).and_then(|node| node.as_mut())
+    }
+
+    fn node(&self, id: NodeId) -> Result<&GameNode, GameTreeError> {
+        self.get_node(id).ok_or(GameTreeError::NodeNotFound(id))
+    }
+
+    /// Play `_move` from node `parent`. A new node becomes the last variation, or the mainline
+    /// continuation if `parent` has no children yet. If the move is already there its node is
+    /// returned instead.
+    pub fn add_move(&mut self, parent: NodeId, _move: Move) -> Result<NodeId, GameTreeError> {
+        let parent_node = self.node(parent)?;
+        if let Some(&existing) = parent_node
+            .children
+            .iter()
+            .find(|&&child| self.nodes[child].as_ref().unwrap()._move == Some(_move))
+        {
+            return Ok(existing);
+        }
+
+        let mut bitboard = parent_node.bitboard;
+        let mut legal_moves = MoveList::new();
+        generate_legal_moves(&bitboard, &mut legal_moves);
+        if !legal_moves.contains(&_move) {
+            return Err(GameTreeError::IllegalMove(_move));
+        }
+        bitboard.make_move(_move);
+
+        let id = self.nodes.len();
+        self.nodes.push(Some(GameNode {
+            _move: Some(_move),
+            bitboard,
+            parent: Some(parent),
+            children: Vec::new(),
+            comment_before: None,
+            comment: None,
+            nags: Vec::new(),
+        }));
+        self.nodes[parent].as_mut().unwrap().children.push(id);
+        Ok(id)
+    }
+
+    /// Move the variation starting at `id` one place up among its siblings, making it the
+    /// continuation when it was the first variation.
+    pub fn promote_variation(&mut self, id: NodeId) -> Result<(), GameTreeError> {
+        let parent = self.node(id)?.parent.ok_or(GameTreeError::RootNode)?;
+        let children = &mut self.nodes[parent].as_mut().unwrap().children;
+        let index = children.iter().position(|&child| child == id).unwrap();
+        if index > 0 {
+            children.swap(index, index - 1);
+        }
+        Ok(())
+    }
+
+    /// Make the line through `id` the mainline all the way from the root.
+    pub fn promote_to_mainline(&mut self, id: NodeId) -> Result<(), GameTreeError> {
+        self.node(id)?;
+        let mut current = id;
+        while let Some(parent) = self.nodes[current].as_ref().unwrap().parent {
+            let children = &mut self.nodes[parent].as_mut().unwrap().children;
+            let index = children.iter().position(|&child| child == current).unwrap();
+            let child = children.remove(index);
+            children.insert(0, child);
+            current = parent;
+        }
+        Ok(())
+    }
+
+    /// Remove node `id` together with everything played after it.
+    pub fn delete_variation(&mut self, id: NodeId) -> Result<(), GameTreeError> {
+        let parent = self.node(id)?.parent.ok_or(GameTreeError::RootNode)?;
+        self.nodes[parent]
+            .as_mut()
+            .unwrap()
+            .children
+            .retain(|&child| child != id);
+
+        let mut stack = vec![id];
+        while let Some(current) = stack.pop() {
+            if let Some(node) = self.nodes[current].take() {
+                stack.extend(node.children);
+            }
+        }
+        Ok(())
+    }
+
+    /// Nodes of the mainline after the root, in playing order.
+    pub fn mainline(&self) -> Vec<NodeId> {
+        self.follow_line(GameTree::ROOT)
+    }
+
+    pub fn mainline_moves(&self) -> Vec<Move> {
+        self.mainline()
+            .into_iter()
+            .filter_map(|id| self.nodes[id].as_ref().unwrap()._move)
+            .collect()
+    }
+
+    /// Every line from the root to a leaf, mainline first.
+    pub fn lines(&self) -> Vec<Vec<NodeId>> {
+        let mut lines = Vec::new();
+        self.collect_lines(GameTree::ROOT, &mut Vec::new(), &mut lines);
+        lines
+    }
+
+    fn collect_lines(&self, id: NodeId, path: &mut Vec<NodeId>, lines: &mut Vec<Vec<NodeId>>) {
+        let node = self.nodes[id].as_ref().unwrap();
+        if node.children.is_empty() {
+            lines.push(path.clone());
+            return;
+        }
+        for &child in &node.children {
+            path.push(child);
+            self.collect_lines(child, path, lines);
+            path.pop();
+        }
+    }
+
+    /// Follow first children below `id`, not including `id` itself.
+    fn follow_line(&self, id: NodeId) -> Vec<NodeId> {
+        let mut line = Vec::new();
+        let mut current = id;
+        while let Some(&child) = self.nodes[current].as_ref().unwrap().children.first() {
+            line.push(child);
+            current = child;
+        }
+        line
+    }
+
+    fn add_pgn_moves(&mut self, parent: NodeId, moves: &[PgnMove]) -> Result<(), GameTreeError> {
+        let mut current = parent;
+        for pgn_move in moves {
+            let id = self.add_move(current, pgn_move._move)?;
+            let node = self.nodes[id].as_mut().unwrap();
+            node.comment_before = pgn_move.comment_before.clone();
+            node.comment = pgn_move.comment.clone();
+            node.nags = pgn_move.nags.clone();
+
+            for variation in &pgn_move.variations {
+                self.add_pgn_moves(current, variation)?;
+            }
+            current = id;
+        }
+        Ok(())
+    }
+
+    /// The line starting with node `first`, with the siblings of every continuation node written
+    /// as its variations.
+    fn to_pgn_moves(&self, first: NodeId) -> Vec<PgnMove> {
+        let mut moves = Vec::new();
+        let mut current = Some(first);
+        while let Some(id) = current {
+            let node = self.nodes[id].as_ref().unwrap();
+            let mut pgn_move = PgnMove::new(node._move.unwrap());
+            pgn_move.comment_before = node.comment_before.clone();
+            pgn_move.comment = node.comment.clone();
+            pgn_move.nags = node.nags.clone();
+
+            let siblings = &self.nodes[node.parent.unwrap()].as_ref().unwrap().children;
+            if siblings[0] == id {
+                pgn_move.variations = siblings[1..]
+                    .iter()
+                    .map(|&sibling| self.to_pgn_moves(sibling))
+                    .collect();
+            }
+
+            moves.push(pgn_move);
+            current = node.children.first().copied();
+        }
+        moves
+    }
+
+    pub fn from_game(game: &Game) -> Result<GameTree, GameTreeError> {
+        let mut tree = GameTree::new(game.start_position);
+        tree.headers = game.headers.clone();
+        tree.result = game.result;
+        tree.add_pgn_moves(GameTree::ROOT, &game.moves)?;
+        Ok(tree)
+    }
+
+    pub fn to_game(&self) -> Game {
+        let root = self.nodes[GameTree::ROOT].as_ref().unwrap();
+        Game {
+            headers: self.headers.clone(),
+            start_position: root.bitboard,
+            moves: root
+                .children
+                .first()
+                .map_or_else(Vec::new, |&first| self.to_pgn_moves(first)),
+            result: self.result,
+        }
+    }
+}
+
+/// Parse the first game of a PGN string, including its recursive annotation variations.
+impl FromStr for GameTree {
+    type Err = GameTreeError;
+
+    fn from_str(pgn: &str) -> Result<GameTree, GameTreeError> {
+        let game = pgn.parse::<Game>().map_err(GameTreeError::PGNParse)?;
+        GameTree::from_game(&game)
+    }
+}
+
+impl fmt::Display for GameTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_game())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::bitboard::STARTING_POSITION_FEN;
+
+    const ANNOTATED_GAME: &str = r#"[Event "Annotated"]
+[Site "?"]
+[Date "????.??.??"]
+[Round "?"]
+[White "?"]
+[Black "?"]
+[Result "1-0"]
+
+{Start} 1. e4 $1 e5 (1... c5 {Open} 2. Nf3 (2. Nc3) (2. c3) 2... d6) (1... e6)
+2. Qh5 Nc6 3. Bc4 Nf6 $4 4. Qxf7# 1-0
+"#;
+
+    fn uci_moves(tree: &GameTree, line: &[NodeId]) -> Vec<String> {
+        line.iter()
+            .map(|&id| tree.get_node(id).unwrap().get_move().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_pgn_round_trip() {
+        let tree = ANNOTATED_GAME.parse::<GameTree>().unwrap();
+        assert_eq!(tree.to_string(), ANNOTATED_GAME);
+        assert_eq!(tree.mainline().len(), 7);
+        assert_eq!(tree.lines().len(), 5);
+
+        let c5 = tree.get_node(GameTree::ROOT).unwrap().get_children()[0];
+        let c5 = tree.get_node(c5).unwrap().get_children()[1];
+        assert_eq!(tree.get_node(c5).unwrap().comment.as_deref(), Some("Open"));
+        assert_eq!(
+            tree.get_node(c5).unwrap().get_bitboard().to_str(),
+            "rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR w KQkq c6 0 2"
+        );
+    }
+
+    #[test]
+    fn test_edit_variations() {
+        let mut tree = GameTree::new(STARTING_POSITION_FEN.parse().unwrap());
+        let bitboard = *tree.get_node(GameTree::ROOT).unwrap().get_bitboard();
+        let e4 = tree
+            .add_move(GameTree::ROOT, bitboard.parse_uci_move("e2e4").unwrap())
+            .unwrap();
+        let d4 = tree
+            .add_move(GameTree::ROOT, bitboard.parse_uci_move("d2d4").unwrap())
+            .unwrap();
+        let d4_again = tree
+            .add_move(GameTree::ROOT, bitboard.parse_uci_move("d2d4").unwrap())
+            .unwrap();
+        assert_eq!(d4, d4_again);
+
+        let d5 = tree.add_move(d4, "d7d5".parse::<Move>().unwrap()).unwrap();
+        let c5 = tree.add_move(e4, "c7c5".parse::<Move>().unwrap()).unwrap();
+        assert_eq!(uci_moves(&tree, &tree.mainline()), ["e2e4", "c7c5"]);
+
+        tree.promote_variation(d4).unwrap();
+        assert_eq!(uci_moves(&tree, &tree.mainline()), ["d2d4", "d7d5"]);
+        tree.promote_to_mainline(c5).unwrap();
+        assert_eq!(uci_moves(&tree, &tree.mainline()), ["e2e4", "c7c5"]);
+        assert_eq!(tree.lines().len(), 2);
+
+        tree.delete_variation(d4).unwrap();
+        assert_eq!(tree.lines().len(), 1);
+        assert_eq!(tree.get_node(d5), None);
+        assert_eq!(
+            tree.add_move(d5, "e2e4".parse::<Move>().unwrap()),
+            Err(GameTreeError::NodeNotFound(d5))
+        );
+        assert_eq!(
+            tree.add_move(c5, "e2e4".parse::<Move>().unwrap()),
+            Err(GameTreeError::IllegalMove("e2e4".parse::<Move>().unwrap()))
+        );
+        assert_eq!(
+            tree.delete_variation(GameTree::ROOT),
+            Err(GameTreeError::RootNode)
+        );
+    }
+}
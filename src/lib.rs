@@ -1,5 +1,19 @@
 pub mod attack_tables;
 pub mod bitboard;
+pub mod engine;
+pub mod epd;
+pub mod evaluation;
+pub mod game_tree;
 pub mod move_generation;
 pub mod move_performing;
+pub mod outcome;
+pub mod perft;
+pub mod pgn;
+pub mod polyglot;
+pub mod san;
+pub mod search;
+pub mod transposition_table;
+pub mod uci;
 pub mod utils;
+pub mod xboard;
+pub mod zobrist;
@@ -1,3 +1,23 @@
+use r_chess::{engine::Output, uci, xboard};
+
+use std::{
+    io::{self, BufRead, BufReader, Cursor, Read},
+    sync::{Arc, Mutex},
+};
+
+/// Speak XBoard if the GUI opens with `xboard`, UCI otherwise.
 fn main() {
-    println!("Hello world");
+    let mut input = BufReader::new(io::stdin());
+    let output: Output = Arc::new(Mutex::new(io::stdout()));
+
+    let mut first_line = String::new();
+    if input.read_line(&mut first_line).is_err() {
+        return;
+    }
+    let protocol = first_line.trim().to_string();
+    let input = Cursor::new(first_line).chain(input);
+    match protocol.as_str() {
+        "xboard" => xboard::run(input, output),
+        _ => uci::run(input, output),
+    }
 }
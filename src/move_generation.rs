@@ -1,14 +1,625 @@
 use crate::{
-    attack_tables::get_king_attacks,
-    bitboard::{self, Bitboard},
-    utils::_move::Move,
+    attack_tables::{
+        get_bishop_attacks, get_double_pawn_moves, get_king_attacks, get_knight_attacks, get_line,
+        get_pawn_attacks, get_pawn_moves, get_queen_attacks, get_rook_attacks, get_squares_between,
+    },
+    bitboard::Bitboard,
+    move_performing::perform_move,
+    utils::{
+        _move::Move,
+        board_slice::BoardSlice,
+        enums::{rank_mask, CastleMoves, Color, Piece, Rank, Square},
+    },
 };
-fn generate_legal_moves(buffer: &mut Vec<Move>, bitboard: Bitboard) {
-    if bitboard.is_king_in_check(bitboard.to_move) {
-        let king_square = bitboard.get_king_square(bitboard.to_move);
-        let possible_king_moves = get_king_attacks(king_square) & bitboard.get_empty_squares();
-        let legal_king_moves = possible_king_moves
-            .iter()
-            .filter(|&square| !bitboard.is_square_attacked(bitboard.to_move.opposite(), square));
+
+pub type MoveList = Vec<Move>;
+
+const PROMOTION_PIECES: [Piece; 4] = [Piece::Queen, Piece::Rook, Piece::Bishop, Piece::Knight];
+
+/// Subsets of the legal moves that `generate_moves` can produce.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GenType {
+    /// Captures, en passant and every promotion, capturing or not.
+    Captures,
+    /// Non-capturing, non-promoting moves, including castling.
+    Quiets,
+    /// Every legal move when the side to move is in check, nothing otherwise.
+    Evasions,
+    /// The quiet moves that give check.
+    QuietChecks,
+    /// Every legal move.
+    All,
+}
+
+/// Append every legal move of the side to move to `buffer`.
+/// Castling moves are encoded as the king moving two squares, or as the king capturing its own
+/// rook in Chess960 mode, and promotions carry the piece promoted to, matching what
+/// `perform_move` expects.
+pub fn generate_legal_moves(bitboard: &Bitboard, buffer: &mut MoveList) {
+    generate_moves(bitboard, GenType::All, buffer);
+}
+
+/// Append the legal moves of the side to move that belong to `gen_type` to `buffer`.
+pub fn generate_moves(bitboard: &Bitboard, gen_type: GenType, buffer: &mut MoveList) {
+    match gen_type {
+        GenType::Captures => generate_staged_moves(bitboard, true, false, buffer),
+        GenType::Quiets => generate_staged_moves(bitboard, false, true, buffer),
+        GenType::Evasions => {
+            if bitboard.is_king_in_check(bitboard.to_move) {
+                generate_staged_moves(bitboard, true, true, buffer);
+            }
+        }
+        GenType::QuietChecks => {
+            let mut quiets = MoveList::new();
+            generate_staged_moves(bitboard, false, true, &mut quiets);
+            buffer.extend(
+                quiets
+                    .into_iter()
+                    .filter(|&_move| gives_check(bitboard, _move)),
+            );
+        }
+        GenType::All => generate_staged_moves(bitboard, true, true, buffer),
+    }
+}
+
+fn gives_check(bitboard: &Bitboard, _move: Move) -> bool {
+    match perform_move(bitboard, &_move) {
+        Ok(new_bitboard) => new_bitboard.is_king_in_check(new_bitboard.to_move),
+        Err(_) => false,
+    }
+}
+
+/// Legal move generation restricted to captures (including every promotion), quiet moves, or
+/// both.
+fn generate_staged_moves(bitboard: &Bitboard, captures: bool, quiets: bool, buffer: &mut MoveList) {
+    let us = bitboard.to_move;
+    let them = us.opposite();
+
+    let occupancy = bitboard.get_all_pieces();
+    let enemy_pieces = bitboard.get_color_pieces(them);
+    let promotion_rank = get_promotion_rank(us);
+
+    // Destinations allowed by the requested stage for pieces other than pawns.
+    let mut stage_mask = BoardSlice(0);
+    if captures {
+        stage_mask = stage_mask | enemy_pieces;
+    }
+    if quiets {
+        stage_mask = stage_mask | !occupancy;
+    }
+
+    let king_square = bitboard.get_king_square(us);
+    let king_slice = BoardSlice(1 << king_square as usize);
+    let checkers = bitboard.get_attackers(them, king_square, occupancy);
+
+    // The king is taken off the board so it cannot hide behind itself from a slider.
+    for dest in (get_king_attacks(king_square) & stage_mask).iter() {
+        if bitboard
+            .get_attackers(them, dest, occupancy & !king_slice)
+            .0
+            == 0
+        {
+            push_move(buffer, king_square, dest);
+        }
+    }
+
+    // In double check only the king can move.
+    if checkers.0.count_ones() > 1 {
+        return;
+    }
+
+    // Squares a non-king move must land on: anywhere if not in check, otherwise capturing the
+    // checker or blocking it.
+    let check_mask = match checkers.iter().next() {
+        Some(checker) => get_squares_between(king_square, checker) | checkers,
+        None => BoardSlice(!0),
+    };
+
+    let pinned = get_pinned_pieces(bitboard, us, king_square);
+    let target_mask = |orig: Square| {
+        if pinned.0 & (1 << orig as usize) != 0 {
+            check_mask & get_line(king_square, orig)
+        } else {
+            check_mask
+        }
+    };
+
+    // Pawns, where pushes to the last rank count as captures rather than quiet moves
+    let mut pawn_push_mask = BoardSlice(0);
+    if captures {
+        pawn_push_mask = pawn_push_mask | promotion_rank;
+    }
+    if quiets {
+        pawn_push_mask = pawn_push_mask | !promotion_rank;
+    }
+    for orig in bitboard.get_piece(us, Piece::Pawn).iter() {
+        let (single_push, double_push) = get_pawn_pushes(us, orig, occupancy);
+        let mut destinations = (single_push | double_push) & pawn_push_mask;
+        if captures {
+            destinations = destinations | (get_pawn_attacks(us, orig) & enemy_pieces);
+        }
+
+        push_pawn_moves(
+            buffer,
+            orig,
+            destinations & target_mask(orig),
+            promotion_rank,
+        );
+
+        if let (true, Some(en_passant_square)) = (captures, bitboard.en_passant_square) {
+            if get_pawn_attacks(us, orig).0 & (1 << en_passant_square as usize) != 0
+                && is_legal_en_passant(bitboard, orig, en_passant_square, king_square)
+            {
+                push_move(buffer, orig, en_passant_square);
+            }
+        }
+    }
+
+    // Knights, bishops, rooks and queens
+    for orig in bitboard.get_piece(us, Piece::Knight).iter() {
+        for dest in (get_knight_attacks(orig) & stage_mask & target_mask(orig)).iter() {
+            push_move(buffer, orig, dest);
+        }
+    }
+    for orig in bitboard.get_piece(us, Piece::Bishop).iter() {
+        for dest in (get_bishop_attacks(orig, occupancy) & stage_mask & target_mask(orig)).iter() {
+            push_move(buffer, orig, dest);
+        }
+    }
+    for orig in bitboard.get_piece(us, Piece::Rook).iter() {
+        for dest in (get_rook_attacks(orig, occupancy) & stage_mask & target_mask(orig)).iter() {
+            push_move(buffer, orig, dest);
+        }
+    }
+    for orig in bitboard.get_piece(us, Piece::Queen).iter() {
+        for dest in (get_queen_attacks(orig, occupancy) & stage_mask & target_mask(orig)).iter() {
+            push_move(buffer, orig, dest);
+        }
+    }
+
+    // Castling. The king's destination is checked with king and rook already moved, since in
+    // Chess960 the rook may be what shields it.
+    if quiets && checkers.0 == 0 {
+        for castle_move in get_castles(us) {
+            if !is_castle_unobstructed(bitboard, castle_move, king_square) {
+                continue;
+            }
+            let (king_dest, rook_orig, rook_dest) = bitboard.get_castling_squares(castle_move);
+            let occupancy_after = (occupancy & !king_slice & !BoardSlice(1 << rook_orig as usize))
+                | BoardSlice(1 << king_dest as usize | 1 << rook_dest as usize);
+            if get_squares_between(king_square, king_dest)
+                .iter()
+                .all(|square| !bitboard.is_square_attacked(them, square))
+                && bitboard.get_attackers(them, king_dest, occupancy_after).0 == 0
+            {
+                push_move(buffer, king_square, get_castle_dest(bitboard, castle_move));
+            }
+        }
+    }
+}
+
+/// Append every move of the side to move that follows the piece movement rules, without
+/// checking whether it leaves the king attacked. Castling only requires the right and an empty
+/// path here; `Bitboard::is_legal` checks the squares the king crosses.
+pub fn generate_pseudo_legal_moves(bitboard: &Bitboard, buffer: &mut MoveList) {
+    let us = bitboard.to_move;
+
+    let occupancy = bitboard.get_all_pieces();
+    let own_pieces = bitboard.get_color_pieces(us);
+    let enemy_pieces = bitboard.get_color_pieces(us.opposite());
+    let targets = !own_pieces;
+
+    // Pawns
+    let promotion_rank = get_promotion_rank(us);
+    let en_passant_slice = match bitboard.en_passant_square {
+        Some(square) => BoardSlice(1 << square as usize),
+        None => BoardSlice(0),
+    };
+    for orig in bitboard.get_piece(us, Piece::Pawn).iter() {
+        let (single_push, double_push) = get_pawn_pushes(us, orig, occupancy);
+        let captures = get_pawn_attacks(us, orig) & (enemy_pieces | en_passant_slice);
+
+        push_pawn_moves(
+            buffer,
+            orig,
+            single_push | double_push | captures,
+            promotion_rank,
+        );
+    }
+
+    // Knights, bishops, rooks, queens and king
+    for orig in bitboard.get_piece(us, Piece::Knight).iter() {
+        for dest in (get_knight_attacks(orig) & targets).iter() {
+            push_move(buffer, orig, dest);
+        }
+    }
+    for orig in bitboard.get_piece(us, Piece::Bishop).iter() {
+        for dest in (get_bishop_attacks(orig, occupancy) & targets).iter() {
+            push_move(buffer, orig, dest);
+        }
+    }
+    for orig in bitboard.get_piece(us, Piece::Rook).iter() {
+        for dest in (get_rook_attacks(orig, occupancy) & targets).iter() {
+            push_move(buffer, orig, dest);
+        }
+    }
+    for orig in bitboard.get_piece(us, Piece::Queen).iter() {
+        for dest in (get_queen_attacks(orig, occupancy) & targets).iter() {
+            push_move(buffer, orig, dest);
+        }
+    }
+    for orig in bitboard.get_piece(us, Piece::King).iter() {
+        for dest in (get_king_attacks(orig) & targets).iter() {
+            push_move(buffer, orig, dest);
+        }
+    }
+
+    // Castling
+    let king_square = bitboard.get_king_square(us);
+    for castle_move in get_castles(us) {
+        if is_castle_unobstructed(bitboard, castle_move, king_square) {
+            push_move(buffer, king_square, get_castle_dest(bitboard, castle_move));
+        }
+    }
+}
+
+fn get_castles(color: Color) -> [CastleMoves; 2] {
+    [
+        CastleMoves::new(color, true),
+        CastleMoves::new(color, false),
+    ]
+}
+
+/// Whether the right is held, the rook is in place and every square the king or rook crosses or
+/// lands on is empty apart from the king and rook themselves.
+fn is_castle_unobstructed(
+    bitboard: &Bitboard,
+    castle_move: CastleMoves,
+    king_square: Square,
+) -> bool {
+    let (king_dest, rook_orig, rook_dest) = bitboard.get_castling_squares(castle_move);
+    let path = get_squares_between(king_square, king_dest)
+        | get_squares_between(rook_orig, rook_dest)
+        | BoardSlice(1 << king_dest as usize | 1 << rook_dest as usize);
+    let castlers = BoardSlice(1 << king_square as usize | 1 << rook_orig as usize);
+
+    bitboard.has_castling_right(castle_move)
+        && king_square as u8 / 8 == king_dest as u8 / 8
+        && bitboard.get_piece(bitboard.to_move, Piece::Rook).0 & (1 << rook_orig as usize) != 0
+        && (path & !castlers & bitboard.get_all_pieces()).0 == 0
+}
+
+/// Destination a castle is encoded with: the king's square after castling, or the rook's square
+/// in Chess960 mode.
+fn get_castle_dest(bitboard: &Bitboard, castle_move: CastleMoves) -> Square {
+    let (king_dest, rook_orig, _) = bitboard.get_castling_squares(castle_move);
+    if bitboard.chess960 {
+        rook_orig
+    } else {
+        king_dest
+    }
+}
+
+fn get_promotion_rank(color: Color) -> BoardSlice {
+    match color {
+        Color::White => rank_mask(Rank::Rank8),
+        Color::Black => rank_mask(Rank::Rank1),
+    }
+}
+
+/// Single and double push destinations of a pawn, stopping at the first occupied square.
+fn get_pawn_pushes(color: Color, orig: Square, occupancy: BoardSlice) -> (BoardSlice, BoardSlice) {
+    let single_push = get_pawn_moves(color, orig) & !occupancy;
+    let double_push = if single_push.0 != 0 {
+        get_double_pawn_moves(color, orig) & !occupancy
+    } else {
+        BoardSlice(0)
+    };
+    (single_push, double_push)
+}
+
+/// Pieces of `color` that are the only thing standing between their king and an enemy slider.
+fn get_pinned_pieces(bitboard: &Bitboard, color: Color, king_square: Square) -> BoardSlice {
+    let them = color.opposite();
+    let occupancy = bitboard.get_all_pieces();
+    let enemy_pieces = bitboard.get_color_pieces(them);
+    let enemy_queens = bitboard.get_piece(them, Piece::Queen);
+
+    let snipers = (get_rook_attacks(king_square, enemy_pieces)
+        & (bitboard.get_piece(them, Piece::Rook) | enemy_queens))
+        | (get_bishop_attacks(king_square, enemy_pieces)
+            & (bitboard.get_piece(them, Piece::Bishop) | enemy_queens));
+
+    snipers.iter().fold(BoardSlice(0), |pinned, sniper| {
+        let blockers = get_squares_between(king_square, sniper) & occupancy;
+        if blockers.0.count_ones() == 1 {
+            pinned | (blockers & bitboard.get_color_pieces(color))
+        } else {
+            pinned
+        }
+    })
+}
+
+/// En passant removes two pieces from the same rank at once, so it is checked by replaying the
+/// occupancy change rather than through the pin mask.
+fn is_legal_en_passant(
+    bitboard: &Bitboard,
+    orig: Square,
+    en_passant_square: Square,
+    king_square: Square,
+) -> bool {
+    let them = bitboard.to_move.opposite();
+    let captured_square = match bitboard.to_move {
+        Color::White => en_passant_square as usize - 8,
+        Color::Black => en_passant_square as usize + 8,
+    };
+    let captured_slice = BoardSlice(1 << captured_square);
+
+    let occupancy = BoardSlice(
+        (bitboard.get_all_pieces().0 & !(1 << orig as usize) & !captured_slice.0)
+            | 1 << en_passant_square as usize,
+    );
+
+    (bitboard.get_attackers(them, king_square, occupancy) & !captured_slice).0 == 0
+}
+
+fn push_move(buffer: &mut MoveList, orig: Square, dest: Square) {
+    buffer.push(Move {
+        orig,
+        dest,
+        promotion: None,
+    });
+}
+
+fn push_pawn_moves(
+    buffer: &mut MoveList,
+    orig: Square,
+    destinations: BoardSlice,
+    promotion_rank: BoardSlice,
+) {
+    for dest in destinations.iter() {
+        if promotion_rank.0 & (1 << dest as usize) != 0 {
+            push_promotions(buffer, orig, dest);
+        } else {
+            push_move(buffer, orig, dest);
+        }
+    }
+}
+
+fn push_promotions(buffer: &mut MoveList, orig: Square, dest: Square) {
+    for piece in PROMOTION_PIECES {
+        buffer.push(Move {
+            orig,
+            dest,
+            promotion: Some(piece),
+        });
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    const TEST_POSITIONS: [&str; 5] = [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "r3k2r/8/8/8/8/8/5r2/R3K2R w KQkq - 0 1",
+        "8/8/8/K2pP2r/8/8/8/7k w - d6 0 1",
+    ];
+
+    fn legal_moves(position_fen: &str) -> MoveList {
+        let bitboard = position_fen.parse::<Bitboard>().unwrap();
+        let mut moves = Vec::new();
+        generate_legal_moves(&bitboard, &mut moves);
+        moves
+    }
+
+    fn contains(moves: &[Move], orig: Square, dest: Square) -> bool {
+        moves.iter().any(|m| m.orig == orig && m.dest == dest)
+    }
+
+    #[test]
+    fn test_start_position() {
+        let moves = legal_moves("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert_eq!(moves.len(), 20);
+    }
+
+    #[test]
+    fn test_kiwipete() {
+        let moves =
+            legal_moves("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+        assert_eq!(moves.len(), 48);
+        assert!(contains(&moves, Square::E1, Square::G1));
+        assert!(contains(&moves, Square::E1, Square::C1));
+    }
+
+    #[test]
+    fn test_pinned_piece() {
+        // The e2 knight is pinned by the e8 rook and cannot move at all.
+        let moves = legal_moves("4r2k/8/8/8/8/8/4N3/4K3 w - - 0 1");
+        assert!(moves.iter().all(|m| m.orig != Square::E2));
+
+        // A pinned rook may still slide along the pin and capture the pinner.
+        let moves = legal_moves("4r2k/8/8/8/8/8/4R3/4K3 w - - 0 1");
+        assert!(contains(&moves, Square::E2, Square::E8));
+        assert!(!contains(&moves, Square::E2, Square::D2));
+    }
+
+    #[test]
+    fn test_double_check() {
+        let moves = legal_moves("4r2k/8/8/8/8/5n2/3Q4/4K3 w - - 0 1");
+        assert!(moves.iter().all(|m| m.orig == Square::E1));
+        assert_eq!(moves.len(), 3);
+    }
+
+    #[test]
+    fn test_check_evasion() {
+        // Only blocking with the bishop, capturing with the queen or moving the king are legal.
+        let moves = legal_moves("4r1k1/8/8/8/8/8/2B5/Q3K3 w - - 0 1");
+        assert!(contains(&moves, Square::C2, Square::E4));
+        assert!(!contains(&moves, Square::C2, Square::D3));
+        assert!(contains(&moves, Square::A1, Square::E5));
+        assert!(!contains(&moves, Square::A1, Square::A8));
+    }
+
+    #[test]
+    fn test_en_passant_discovered_check() {
+        // Capturing en passant would expose the king on the fifth rank.
+        let moves = legal_moves("8/8/8/K2pP2r/8/8/8/7k w - d6 0 1");
+        assert!(!contains(&moves, Square::E5, Square::D6));
+
+        let moves = legal_moves("8/8/8/3pP3/8/8/K7/7k w - d6 0 1");
+        assert!(contains(&moves, Square::E5, Square::D6));
+    }
+
+    #[test]
+    fn test_castling_through_check() {
+        let moves = legal_moves("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        assert!(contains(&moves, Square::E1, Square::G1));
+        assert!(contains(&moves, Square::E1, Square::C1));
+
+        // The f1 square is attacked, so only queenside castling is allowed.
+        let moves = legal_moves("r3k2r/8/8/8/8/8/5r2/R3K2R w KQkq - 0 1");
+        assert!(!contains(&moves, Square::E1, Square::G1));
+        assert!(contains(&moves, Square::E1, Square::C1));
+
+        // The b1 square may be attacked when castling queenside.
+        let moves = legal_moves("r3k2r/8/8/8/8/8/1r6/R3K2R w KQkq - 0 1");
+        assert!(contains(&moves, Square::E1, Square::C1));
+    }
+
+    #[test]
+    fn test_chess960_castling() {
+        // Castling is encoded as the king capturing its own rook, even when the king only moves
+        // one square.
+        let moves = legal_moves("4k3/8/8/8/8/8/8/1R2K1R1 w GB - 0 1");
+        assert!(contains(&moves, Square::E1, Square::G1));
+        assert!(contains(&moves, Square::E1, Square::B1));
+        assert!(!contains(&moves, Square::E1, Square::C1));
+
+        // The squares the rook crosses must be empty too.
+        let moves = legal_moves("4k3/8/8/8/8/8/8/1RN1K1R1 w GB - 0 1");
+        assert!(!contains(&moves, Square::E1, Square::B1));
+
+        // The castling rook shields c1 from the queen until it moves.
+        let moves = legal_moves("4k3/8/8/8/8/8/8/qR1K4 w B - 0 1");
+        assert!(contains(&moves, Square::D1, Square::C1));
+        assert!(!contains(&moves, Square::D1, Square::B1));
+    }
+
+    #[test]
+    fn test_promotions() {
+        let moves = legal_moves("3r3k/4P3/8/8/8/8/8/K7 w - - 0 1");
+        assert_eq!(
+            moves
+                .iter()
+                .filter(|m| m.orig == Square::E7 && m.promotion.is_some())
+                .count(),
+            8
+        );
+    }
+
+    #[test]
+    fn test_pseudo_legal_moves_filtered_match_legal_moves() {
+        for position_fen in TEST_POSITIONS {
+            let bitboard = position_fen.parse::<Bitboard>().unwrap();
+            let mut pseudo_legal_moves = MoveList::new();
+            generate_pseudo_legal_moves(&bitboard, &mut pseudo_legal_moves);
+
+            let mut filtered: MoveList = pseudo_legal_moves
+                .into_iter()
+                .filter(|&m| bitboard.is_legal(m))
+                .collect();
+            let mut legal = legal_moves(position_fen);
+
+            let key = |m: &Move| (m.orig as u8, m.dest as u8, m.promotion.map(|p| p as u8));
+            filtered.sort_by_key(key);
+            legal.sort_by_key(key);
+            assert_eq!(filtered, legal, "{}", position_fen);
+        }
+    }
+
+    #[test]
+    fn test_pseudo_legal_moves_include_illegal() {
+        // The pinned knight still generates its jumps.
+        let bitboard = "4r2k/8/8/8/8/8/4N3/4K3 w - - 0 1"
+            .parse::<Bitboard>()
+            .unwrap();
+        let mut moves = MoveList::new();
+        generate_pseudo_legal_moves(&bitboard, &mut moves);
+        assert!(contains(&moves, Square::E2, Square::C3));
+        assert!(!bitboard.is_legal(Move {
+            orig: Square::E2,
+            dest: Square::C3,
+            promotion: None
+        }));
+    }
+
+    fn staged_moves(position_fen: &str, gen_type: GenType) -> MoveList {
+        let bitboard = position_fen.parse::<Bitboard>().unwrap();
+        let mut moves = MoveList::new();
+        generate_moves(&bitboard, gen_type, &mut moves);
+        moves
+    }
+
+    #[test]
+    fn test_captures_and_quiets_partition_legal_moves() {
+        for position_fen in TEST_POSITIONS {
+            let legal = legal_moves(position_fen);
+            let captures = staged_moves(position_fen, GenType::Captures);
+            let quiets = staged_moves(position_fen, GenType::Quiets);
+
+            assert_eq!(
+                captures.len() + quiets.len(),
+                legal.len(),
+                "{}",
+                position_fen
+            );
+            assert!(captures
+                .iter()
+                .all(|m| legal.contains(m) && !quiets.contains(m)));
+            assert!(quiets.iter().all(|m| legal.contains(m)));
+        }
+    }
+
+    #[test]
+    fn test_captures() {
+        // Kiwipete has eight captures.
+        let captures = staged_moves(TEST_POSITIONS[1], GenType::Captures);
+        assert_eq!(captures.len(), 8);
+
+        // Quiet promotions and en passant are part of the captures stage.
+        let captures = staged_moves("3r3k/4P3/8/8/8/8/8/K7 w - - 0 1", GenType::Captures);
+        assert_eq!(captures.len(), 8);
+        let captures = staged_moves("8/8/8/3pP3/8/8/K7/7k w - d6 0 1", GenType::Captures);
+        assert_eq!(captures.len(), 1);
+    }
+
+    #[test]
+    fn test_evasions() {
+        assert!(staged_moves(TEST_POSITIONS[0], GenType::Evasions).is_empty());
+
+        let position_fen = "4r1k1/8/8/8/8/8/2B5/Q3K3 w - - 0 1";
+        let mut evasions = staged_moves(position_fen, GenType::Evasions);
+        let mut legal = legal_moves(position_fen);
+        evasions.sort_by_key(|m| (m.orig as u8, m.dest as u8));
+        legal.sort_by_key(|m| (m.orig as u8, m.dest as u8));
+        assert_eq!(evasions, legal);
+    }
+
+    #[test]
+    fn test_quiet_checks() {
+        // Castling queenside brings the rook to the d-file.
+        let quiet_checks = staged_moves("3k4/8/8/8/8/8/8/R3K3 w Q - 0 1", GenType::QuietChecks);
+        assert!(contains(&quiet_checks, Square::A1, Square::A8));
+        assert!(contains(&quiet_checks, Square::A1, Square::D1));
+        assert!(contains(&quiet_checks, Square::E1, Square::C1));
+        assert_eq!(quiet_checks.len(), 3);
+
+        // Discovered check by moving the bishop off the rook's file.
+        let quiet_checks = staged_moves("4k3/8/8/8/4B3/8/8/4RK2 w - - 0 1", GenType::QuietChecks);
+        assert!(quiet_checks.iter().all(|m| m.orig == Square::E4));
+        assert_eq!(quiet_checks.len(), 13);
     }
 }
@@ -1,188 +1,250 @@
-use std::{i8, usize};
-
 use int_enum::IntEnum;
 use strum::IntoEnumIterator;
 
 use crate::{
+    attack_tables::{get_double_pawn_moves, get_pawn_attacks},
     bitboard::Bitboard,
     utils::{
-        _move::Move,
-        enums::{CastleMoves, Color, Piece, Square},
+        _move::{Move, MoveFlag, PackedMove},
+        enums::{rank_mask, CastleMoves, Color, Piece, Rank, Square},
         errors::PerformMoveError,
     },
 };
 
+/// Everything `make_move` overwrites that cannot be recomputed from the move itself, so
+/// `unmake_move` can restore the position exactly.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UndoInfo {
+    pub captured_piece: Option<Piece>,
+
+    castling_rights: u8,
+    castling_rook_squares: [Square; 4],
+
+    pub en_passant_square: Option<Square>,
+
+    pub half_move_clock: usize,
+    pub full_move_clock: usize,
+
+    zobrist_key: u64,
+
+    move_flag: MoveFlag,
+}
+
 /// Perform move on bitboard and return correct new bitboard or error if encountered
 /// Function does NOT check for legality of move. Moves that make the pieces move in non-standard
 /// ways may result in the function throwin an error or returning correctly (undefined behavior).
 /// If the piece is moved in a standard way, the function CAN safely be used to check if the move put
-/// one's king in check, making the move illegal.
+/// one's king in check, making the move illegal. `Bitboard::is_legal` relies on exactly this.
+/// Moves that are inconsistent with the position (wrong side, own piece on the destination,
+/// impossible pawn moves or promotions) are rejected with an error instead of being played.
 pub fn perform_move(bitboard: &Bitboard, _move: &Move) -> Result<Bitboard, PerformMoveError> {
-    let mut new_bitboard = bitboard.clone();
+    let (move_color, move_piece) = bitboard
+        .get_piece_at(_move.orig)
+        .ok_or(PerformMoveError::NoPieceOnOrigin(_move.orig))?;
 
-    let move_color = if bitboard.get_color_pieces(Color::White).0 & (1 << _move.orig as usize) != 0
+    if move_color != bitboard.to_move {
+        return Err(PerformMoveError::WrongSideToMove);
+    }
+    if bitboard.get_color_pieces(move_color).0 & (1 << _move.dest as usize) != 0
+        && bitboard.get_castle(_move).is_none()
     {
-        Color::White
-    } else {
-        Color::Black
+        return Err(PerformMoveError::CaptureOwnPiece(_move.dest));
+    }
+
+    let last_rank = match move_color {
+        Color::White => Rank::Rank8,
+        Color::Black => Rank::Rank1,
     };
+    let reaches_last_rank =
+        move_piece == Piece::Pawn && rank_mask(last_rank).0 & (1 << _move.dest as usize) != 0;
+    match _move.promotion {
+        Some(piece @ (Piece::Pawn | Piece::King)) => {
+            return Err(PerformMoveError::InvalidPromotionPiece(piece))
+        }
+        Some(_) if !reaches_last_rank => return Err(PerformMoveError::IllegalPromotion),
+        None if reaches_last_rank => return Err(PerformMoveError::MissingPromotion),
+        _ => {}
+    }
 
-    let move_piece = Piece::iter()
-        .filter(|&piece| bitboard.get_piece(move_color, piece).0 & (1 << _move.orig as usize) != 0)
-        .next()
-        .unwrap();
-
-    // Castling
-    if move_piece == Piece::King && ((_move.orig as i8 - _move.dest as i8).abs() == 2) {
-        if _move.dest == Square::G1 {
-            new_bitboard.move_piece(Color::White, Piece::King, Square::E1, Square::G1);
-            new_bitboard.move_piece(Color::White, Piece::Rook, Square::H1, Square::F1);
-            new_bitboard.remove_castling_right(CastleMoves::WhiteKingsideCastle);
-            new_bitboard.remove_castling_right(CastleMoves::WhiteQueensideCastle);
-        } else if _move.dest == Square::G8 {
-            new_bitboard.move_piece(Color::Black, Piece::King, Square::E8, Square::G8);
-            new_bitboard.move_piece(Color::Black, Piece::Rook, Square::H8, Square::F8);
-            new_bitboard.remove_castling_right(CastleMoves::BlackKingsideCastle);
-            new_bitboard.remove_castling_right(CastleMoves::BlackQueensideCastle);
-        } else if _move.dest == Square::C1 {
-            new_bitboard.move_piece(Color::White, Piece::King, Square::E1, Square::C1);
-            new_bitboard.move_piece(Color::White, Piece::Rook, Square::A1, Square::D1);
-            new_bitboard.remove_castling_right(CastleMoves::WhiteKingsideCastle);
-            new_bitboard.remove_castling_right(CastleMoves::WhiteQueensideCastle);
-        } else if _move.dest == Square::C8 {
-            new_bitboard.move_piece(Color::Black, Piece::King, Square::E8, Square::C8);
-            new_bitboard.move_piece(Color::Black, Piece::Rook, Square::A8, Square::D8);
-            new_bitboard.remove_castling_right(CastleMoves::BlackKingsideCastle);
-            new_bitboard.remove_castling_right(CastleMoves::BlackQueensideCastle);
+    if move_piece == Piece::Pawn {
+        let is_diagonal =
+            get_pawn_attacks(move_color, _move.orig).0 & (1 << _move.dest as usize) != 0;
+        let dest_empty = bitboard.get_all_pieces().0 & (1 << _move.dest as usize) == 0;
+        if is_diagonal && dest_empty && bitboard.en_passant_square != Some(_move.dest) {
+            return Err(PerformMoveError::EnPassantImpossible);
+        }
+
+        let is_double_push = (_move.orig as i8 - _move.dest as i8).abs() == 16;
+        if is_double_push
+            && (get_double_pawn_moves(move_color, _move.orig).0 & (1 << _move.dest as usize) == 0
+                || bitboard.get_all_pieces().0
+                    & (1 << get_square_behind(_move.dest, move_color) as usize)
+                    != 0
+                || !dest_empty)
+        {
+            return Err(PerformMoveError::ImpossibleDoublePush);
         }
-        new_bitboard.toggle_move();
-        new_bitboard.en_passant_square = None;
-        new_bitboard.half_move_clock += 1;
-        new_bitboard.full_move_clock += if new_bitboard.to_move == Color::White {
-            1
-        } else {
-            0
-        };
-        return Ok(new_bitboard);
     }
 
-    // Double push
-    if move_piece == Piece::Pawn && ((_move.orig as i8 - _move.dest as i8).abs() == 16) {
-        new_bitboard.move_piece(move_color, Piece::Pawn, _move.orig, _move.dest);
-
-        new_bitboard.toggle_move();
-        new_bitboard.en_passant_square = Some(match move_color {
-            Color::White => Square::from_int(_move.dest as u8 - 8)
-                .map_err(|_| PerformMoveError::ImpossibleDoublePush),
-            Color::Black => Square::from_int(_move.dest as u8 + 8)
-                .map_err(|_| PerformMoveError::ImpossibleDoublePush),
-        }?);
-        new_bitboard.half_move_clock = 0;
-        new_bitboard.full_move_clock += if new_bitboard.to_move == Color::White {
-            1
-        } else {
-            0
+    let mut new_bitboard = *bitboard;
+    new_bitboard.make_move(*_move);
+    Ok(new_bitboard)
+}
+
+impl Bitboard {
+    /// Play `_move` in place, with the same semantics as `perform_move` but without copying the
+    /// board or validating the move. The returned `UndoInfo` must be handed back to
+    /// `unmake_move` together with the same move to take it back.
+    pub fn make_move(&mut self, _move: Move) -> UndoInfo {
+        self.make_packed_move(PackedMove::from_move(self, &_move))
+    }
+
+    /// `make_move` for a move whose kind is already encoded in its flag.
+    pub fn make_packed_move(&mut self, packed_move: PackedMove) -> UndoInfo {
+        let (orig, dest, move_flag) = (packed_move.orig(), packed_move.dest(), packed_move.flag());
+        let (move_color, move_piece) = self.get_piece_at(orig).unwrap();
+
+        let mut undo_info = UndoInfo {
+            captured_piece: None,
+            castling_rights: self.castling_rights,
+            castling_rook_squares: self.castling_rook_squares,
+            en_passant_square: self.en_passant_square,
+            half_move_clock: self.half_move_clock,
+            full_move_clock: self.full_move_clock,
+            zobrist_key: self.zobrist_key,
+            move_flag,
         };
-        return Ok(new_bitboard);
-    };
+        let mut en_passant_square = None;
+
+        match move_flag {
+            MoveFlag::KingsideCastle | MoveFlag::QueensideCastle => {
+                // In Chess960 the king or rook may land on the other's origin, so both leave the
+                // board before either is put back.
+                let (king_dest, rook_orig, rook_dest) =
+                    self.get_castling_squares(get_castle_move(move_color, move_flag));
+                self.remove_piece(move_color, Piece::King, orig);
+                self.remove_piece(move_color, Piece::Rook, rook_orig);
+                self.add_piece(move_color, Piece::King, king_dest);
+                self.add_piece(move_color, Piece::Rook, rook_dest);
+            }
+            MoveFlag::EnPassant => {
+                self.move_piece(move_color, Piece::Pawn, orig, dest);
+                self.remove_piece(
+                    move_color.opposite(),
+                    Piece::Pawn,
+                    get_square_behind(dest, move_color),
+                );
+                undo_info.captured_piece = Some(Piece::Pawn);
+            }
+            _ => {
+                if packed_move.is_capture() {
+                    let (_, piece) = self.get_piece_at(dest).unwrap();
+                    self.remove_piece(move_color.opposite(), piece, dest);
+                    undo_info.captured_piece = Some(piece);
+                }
+
+                match packed_move.promotion() {
+                    Some(piece) => {
+                        self.remove_piece(move_color, Piece::Pawn, orig);
+                        self.add_piece(move_color, piece, dest);
+                    }
+                    None => self.move_piece(move_color, move_piece, orig, dest),
+                }
+
+                if move_flag == MoveFlag::DoublePawnPush {
+                    en_passant_square = Some(get_square_behind(dest, move_color));
+                }
+            }
+        }
 
-    // En passant
-    if bitboard.en_passant_square == Some(_move.dest) && move_piece == Piece::Pawn {
-        new_bitboard.move_piece(move_color, Piece::Pawn, _move.orig, _move.dest);
-        new_bitboard.remove_piece(
-            move_color.opposite(),
-            Piece::Pawn,
-            match bitboard.en_passant_square {
-                Some(square) => match move_color {
-                    Color::White => Square::from_int(square as u8 - 8)
-                        .map_err(|_| PerformMoveError::EnPassantImpossible),
-                    Color::Black => Square::from_int(square as u8 + 8)
-                        .map_err(|_| PerformMoveError::EnPassantImpossible),
-                }?,
-                None => return Err(PerformMoveError::EnPassantImpossible),
-            },
-        );
+        // Moving the king or a rook off its starting square, or capturing a rook on its starting
+        // square, loses the corresponding castling rights.
+        for cm in CastleMoves::iter() {
+            let rook_square = self.get_castling_rook_square(cm);
+            if self.has_castling_right(cm)
+                && ((move_piece == Piece::King && cm.color() == move_color)
+                    || orig == rook_square
+                    || dest == rook_square)
+            {
+                self.remove_castling_right(cm);
+            }
+        }
 
-        new_bitboard.toggle_move();
-        new_bitboard.en_passant_square = None;
-        new_bitboard.half_move_clock = 0;
-        new_bitboard.full_move_clock += if new_bitboard.to_move == Color::White {
-            1
+        self.toggle_move();
+        self.set_en_passant_square(en_passant_square);
+        self.half_move_clock = if undo_info.captured_piece.is_none() && move_piece != Piece::Pawn {
+            self.half_move_clock + 1
         } else {
             0
         };
-        return Ok(new_bitboard);
-    };
+        self.full_move_clock += if self.to_move == Color::White { 1 } else { 0 };
 
-    // Promotion
-    match _move.promotion {
-        Some(piece) => {
-            new_bitboard.remove_piece(move_color, Piece::Pawn, _move.orig);
-            new_bitboard.add_piece(move_color, piece, _move.dest);
-
-            new_bitboard.toggle_move();
-            new_bitboard.en_passant_square = None;
-            new_bitboard.half_move_clock = 0;
-            new_bitboard.full_move_clock += if new_bitboard.to_move == Color::White {
-                1
-            } else {
-                0
-            };
-            return Ok(new_bitboard);
-        }
-        None => {}
+        debug_assert_eq!(self.get_zobrist_key(), self.compute_zobrist_key());
+        undo_info
     }
 
-    // Normal and capture
-    let capture_piece = Piece::iter()
-        .filter(|&piece| {
-            bitboard.get_piece(move_color.opposite(), piece).0 & (1 << _move.dest as usize) != 0
-        })
-        .next();
-
-    new_bitboard.move_piece(move_color, move_piece, _move.orig, _move.dest);
-
-    match capture_piece {
-        Some(piece) => new_bitboard.remove_piece(move_color.opposite(), piece, _move.dest),
-        None => {}
-    };
-
-    match move_color {
-        Color::White => {
-            if move_piece == Piece::Rook && _move.orig == Square::A1 {
-                new_bitboard.remove_castling_right(CastleMoves::WhiteQueensideCastle);
-            } else if move_piece == Piece::Rook && _move.orig == Square::H1 {
-                new_bitboard.remove_castling_right(CastleMoves::WhiteKingsideCastle);
-            } else if move_piece == Piece::King {
-                new_bitboard.remove_castling_right(CastleMoves::WhiteKingsideCastle);
-                new_bitboard.remove_castling_right(CastleMoves::WhiteQueensideCastle);
+    /// Take back `_move`, which must be the last move played with `make_move`, restoring the
+    /// board to exactly the state it was in before.
+    pub fn unmake_move(&mut self, _move: Move, undo_info: UndoInfo) {
+        self.toggle_move();
+        let move_color = self.to_move;
+        self.castling_rook_squares = undo_info.castling_rook_squares;
+
+        match undo_info.move_flag {
+            MoveFlag::KingsideCastle | MoveFlag::QueensideCastle => {
+                let (king_dest, rook_orig, rook_dest) =
+                    self.get_castling_squares(get_castle_move(move_color, undo_info.move_flag));
+                self.remove_piece(move_color, Piece::King, king_dest);
+                self.remove_piece(move_color, Piece::Rook, rook_dest);
+                self.add_piece(move_color, Piece::King, _move.orig);
+                self.add_piece(move_color, Piece::Rook, rook_orig);
+            }
+            _ => {
+                let (_, moved_piece) = self.get_piece_at(_move.dest).unwrap();
+                if _move.promotion.is_some() {
+                    self.remove_piece(move_color, moved_piece, _move.dest);
+                    self.add_piece(move_color, Piece::Pawn, _move.orig);
+                } else {
+                    self.move_piece(move_color, moved_piece, _move.dest, _move.orig);
+                }
             }
         }
-        Color::Black => {
-            if move_piece == Piece::Rook && _move.orig == Square::A8 {
-                new_bitboard.remove_castling_right(CastleMoves::BlackQueensideCastle);
-            } else if move_piece == Piece::Rook && _move.orig == Square::H8 {
-                new_bitboard.remove_castling_right(CastleMoves::BlackKingsideCastle);
-            } else if move_piece == Piece::King {
-                new_bitboard.remove_castling_right(CastleMoves::BlackKingsideCastle);
-                new_bitboard.remove_castling_right(CastleMoves::BlackQueensideCastle);
+
+        match undo_info.move_flag {
+            MoveFlag::KingsideCastle | MoveFlag::QueensideCastle => {}
+            MoveFlag::EnPassant => self.add_piece(
+                move_color.opposite(),
+                Piece::Pawn,
+                get_square_behind(_move.dest, move_color),
+            ),
+            _ => {
+                if let Some(piece) = undo_info.captured_piece {
+                    self.add_piece(move_color.opposite(), piece, _move.dest);
+                }
             }
         }
-    };
 
-    new_bitboard.toggle_move();
-    new_bitboard.en_passant_square = None;
-    new_bitboard.half_move_clock = if capture_piece == None && move_piece != Piece::Pawn {
-        new_bitboard.half_move_clock + 1
-    } else {
-        0
-    };
-    new_bitboard.full_move_clock += if new_bitboard.to_move == Color::White {
-        1
-    } else {
-        0
-    };
-    Ok(new_bitboard)
+        self.castling_rights = undo_info.castling_rights;
+        self.en_passant_square = undo_info.en_passant_square;
+        self.half_move_clock = undo_info.half_move_clock;
+        self.full_move_clock = undo_info.full_move_clock;
+        self.zobrist_key = undo_info.zobrist_key;
+
+        debug_assert_eq!(self.get_zobrist_key(), self.compute_zobrist_key());
+    }
+}
+
+fn get_castle_move(color: Color, move_flag: MoveFlag) -> CastleMoves {
+    CastleMoves::new(color, move_flag == MoveFlag::KingsideCastle)
+}
+
+/// The square one step back from `square` as seen by `color`: the skipped square of a double
+/// push, or the square of a pawn taken en passant.
+fn get_square_behind(square: Square, color: Color) -> Square {
+    match color {
+        Color::White => Square::from_int(square as u8 - 8).unwrap(),
+        Color::Black => Square::from_int(square as u8 + 8).unwrap(),
+    }
 }
 
 #[cfg(test)]
@@ -194,6 +256,8 @@ pub mod tests {
     };
 
     use super::{perform_move, Move};
+    use crate::move_generation::{generate_legal_moves, MoveList};
+    use crate::utils::errors::PerformMoveError;
 
     #[test]
     fn test_perform_move_opera_game() {
@@ -402,4 +466,187 @@ pub mod tests {
             "1r2k2r/pppppppp/8/8/8/8/PPPPPPPP/R3K2R w KQk - 2 2"
         )
     }
+
+    #[test]
+    fn test_make_move_matches_perform_move() {
+        let position_fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbqkbnr/pp1p2pp/8/2pPpp2/4P3/8/PPP2PPP/RNBQKBNR w KQkq c6 0 4",
+        ];
+        for position_fen in position_fens {
+            let bitboard = position_fen.parse::<Bitboard>().unwrap();
+            let mut moves = MoveList::new();
+            generate_legal_moves(&bitboard, &mut moves);
+
+            for _move in moves {
+                let mut made = bitboard;
+                let undo_info = made.make_move(_move);
+                assert_eq!(made, perform_move(&bitboard, &_move).unwrap(), "{}", _move);
+
+                made.unmake_move(_move, undo_info);
+                assert_eq!(made, bitboard, "{}", _move);
+            }
+        }
+    }
+
+    #[test]
+    fn test_unmake_move_restores_capture_promotion() {
+        let position_fen = "1r2k3/P7/8/8/8/8/8/4K3 w - - 3 40";
+        let mut bitboard = position_fen.parse::<Bitboard>().unwrap();
+        let _move = Move {
+            orig: Square::A7,
+            dest: Square::B8,
+
+            promotion: Some(Piece::Queen),
+        };
+
+        let undo_info = bitboard.make_move(_move);
+        assert_eq!(bitboard.to_str(), "1Q2k3/8/8/8/8/8/8/4K3 b - - 0 40");
+        assert_eq!(undo_info.captured_piece, Some(Piece::Rook));
+
+        bitboard.unmake_move(_move, undo_info);
+        assert_eq!(bitboard.to_str(), position_fen);
+    }
+
+    #[test]
+    fn test_perform_move_capture_promotion() {
+        let position_fen = "rn2k3/1P6/8/8/8/8/8/4K3 w q - 0 30";
+        let bitboard = position_fen.parse::<Bitboard>().unwrap();
+        let move1 = Move {
+            orig: Square::B7,
+            dest: Square::A8,
+
+            promotion: Some(Piece::Knight),
+        };
+        let bitboard = perform_move(&bitboard, &move1).unwrap();
+        assert_eq!(bitboard.to_str(), "Nn2k3/8/8/8/8/8/8/4K3 b - - 0 30")
+    }
+
+    #[test]
+    fn test_remove_castle_rights_on_rook_capture() {
+        let position_fen = "r3k2r/8/8/8/8/8/6b1/R3K2R b KQkq - 4 20";
+        let bitboard = position_fen.parse::<Bitboard>().unwrap();
+        let move1 = Move {
+            orig: Square::G2,
+            dest: Square::H1,
+
+            promotion: None,
+        };
+        let bitboard = perform_move(&bitboard, &move1).unwrap();
+        assert_eq!(bitboard.to_str(), "r3k2r/8/8/8/8/8/8/R3K2b w Qkq - 0 21")
+    }
+
+    #[test]
+    fn test_chess960_castling() {
+        for (position_fen, uci_move, expected_fen) in [
+            (
+                "4k3/8/8/8/8/8/8/1R2K1R1 w KQ - 0 1",
+                "e1g1",
+                "4k3/8/8/8/8/8/8/1R3RK1 b - - 1 1",
+            ),
+            (
+                "4k3/8/8/8/8/8/8/1R2K1R1 w KQ - 0 1",
+                "e1b1",
+                "4k3/8/8/8/8/8/8/2KR2R1 b - - 1 1",
+            ),
+            // King and rook swap squares
+            (
+                "4k3/8/8/8/8/8/8/5KR1 w K - 0 1",
+                "f1g1",
+                "4k3/8/8/8/8/8/8/5RK1 b - - 1 1",
+            ),
+        ] {
+            let mut bitboard = position_fen.parse::<Bitboard>().unwrap();
+            assert!(bitboard.chess960);
+            let original = bitboard;
+
+            let _move = bitboard.parse_uci_move(uci_move).unwrap();
+            assert_eq!(
+                perform_move(&bitboard, &_move).unwrap().to_str(),
+                expected_fen
+            );
+            let undo_info = bitboard.make_move(_move);
+            assert_eq!(bitboard.to_str(), expected_fen);
+            bitboard.unmake_move(_move, undo_info);
+            assert_eq!(bitboard, original);
+        }
+    }
+
+    #[test]
+    fn test_perform_move_castling_clocks() {
+        let position_fen = "r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 7 20";
+        let bitboard = position_fen.parse::<Bitboard>().unwrap();
+        let move1 = Move {
+            orig: Square::E8,
+            dest: Square::G8,
+
+            promotion: None,
+        };
+        let bitboard = perform_move(&bitboard, &move1).unwrap();
+        assert_eq!(bitboard.to_str(), "r4rk1/8/8/8/8/8/8/R3K2R w KQ - 8 21")
+    }
+
+    #[test]
+    fn test_perform_move_errors() {
+        let position_fen = "r3k3/1P6/8/3pP3/8/8/4P3/4K2R w K d6 0 1";
+        let bitboard = position_fen.parse::<Bitboard>().unwrap();
+        let error = |orig: Square, dest: Square, promotion: Option<Piece>| {
+            perform_move(
+                &bitboard,
+                &Move {
+                    orig,
+                    dest,
+                    promotion,
+                },
+            )
+            .unwrap_err()
+        };
+
+        assert_eq!(
+            error(Square::A3, Square::A4, None),
+            PerformMoveError::NoPieceOnOrigin(Square::A3)
+        );
+        assert_eq!(
+            error(Square::A8, Square::A7, None),
+            PerformMoveError::WrongSideToMove
+        );
+        assert_eq!(
+            error(Square::E1, Square::E2, None),
+            PerformMoveError::CaptureOwnPiece(Square::E2)
+        );
+        assert_eq!(
+            error(Square::B7, Square::B8, Some(Piece::King)),
+            PerformMoveError::InvalidPromotionPiece(Piece::King)
+        );
+        assert_eq!(
+            error(Square::B7, Square::A8, Some(Piece::Pawn)),
+            PerformMoveError::InvalidPromotionPiece(Piece::Pawn)
+        );
+        assert_eq!(
+            error(Square::B7, Square::B8, None),
+            PerformMoveError::MissingPromotion
+        );
+        assert_eq!(
+            error(Square::E2, Square::E3, Some(Piece::Queen)),
+            PerformMoveError::IllegalPromotion
+        );
+        assert_eq!(
+            error(Square::E5, Square::F6, None),
+            PerformMoveError::EnPassantImpossible
+        );
+        assert_eq!(
+            error(Square::E5, Square::E7, None),
+            PerformMoveError::ImpossibleDoublePush
+        );
+        assert!(perform_move(
+            &bitboard,
+            &Move {
+                orig: Square::E5,
+                dest: Square::D6,
+                promotion: None
+            }
+        )
+        .is_ok());
+    }
 }
@@ -0,0 +1,300 @@
+use crate::{
+    bitboard::Bitboard,
+    move_generation::{generate_legal_moves, MoveList},
+    pgn::GameResult,
+    utils::enums::{Color, Piece},
+};
+
+use strum::IntoEnumIterator;
+
+const LIGHT_SQUARES: u64 = 0x55AA_55AA_55AA_55AA;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Termination {
+    Checkmate,
+    Stalemate,
+    InsufficientMaterial,
+    SeventyFiveMoves,
+    FivefoldRepetition,
+    FiftyMoves,
+    ThreefoldRepetition,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct GameOutcome {
+    pub termination: Termination,
+    pub winner: Option<Color>,
+}
+
+impl GameOutcome {
+    pub fn result(&self) -> GameResult {
+        match self.winner {
+            Some(Color::White) => GameResult::WhiteWins,
+            Some(Color::Black) => GameResult::BlackWins,
+            None => GameResult::Draw,
+        }
+    }
+}
+
+/// Zobrist keys of the positions of a game, current position last, for repetition detection.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct PositionHistory {
+    keys: Vec<u64>,
+}
+
+impl PositionHistory {
+    pub fn new(bitboard: &Bitboard) -> PositionHistory {
+        PositionHistory {
+            keys: vec![bitboard.get_zobrist_key()],
+        }
+    }
+
+    /// Record the position reached after a move.
+    pub fn push(&mut self, bitboard: &Bitboard) {
+        self.keys.push(bitboard.get_zobrist_key());
+    }
+
+    pub fn pop(&mut self) -> Option<u64> {
+        self.keys.pop()
+    }
+
+    /// How often `bitboard` has occurred, counting it once if it is the last position pushed.
+    /// Only positions since the last capture or pawn move can repeat, so the search stops after
+    /// `half_move_clock` plies.
+    pub fn repetition_count(&self, bitboard: &Bitboard) -> usize {
+        let key = bitboard.get_zobrist_key();
+        self.keys
+            .iter()
+            .rev()
+            .take(bitboard.half_move_clock + 1)
+            .filter(|&&other| other == key)
+            .count()
+    }
+}
+
+impl Bitboard {
+    pub fn is_checkmate(&self) -> bool {
+        self.is_king_in_check(self.to_move) && !self.has_legal_moves()
+    }
+
+    pub fn is_stalemate(&self) -> bool {
+        !self.is_king_in_check(self.to_move) && !self.has_legal_moves()
+    }
+
+    /// Neither side can possibly mate: bare kings, a single minor piece, or only bishops which
+    /// all stand on squares of the same colour.
+    pub fn is_insufficient_material(&self) -> bool {
+        let mut minor_pieces = 0;
+        let mut bishops = 0;
+        for color in Color::iter() {
+            for piece in [Piece::Pawn, Piece::Rook, Piece::Queen] {
+                if self.get_piece(color, piece).0 != 0 {
+                    return false;
+                }
+            }
+            minor_pieces += self.get_piece(color, Piece::Knight).0.count_ones()
+                + self.get_piece(color, Piece::Bishop).0.count_ones();
+            bishops |= self.get_piece(color, Piece::Bishop).0;
+        }
+
+        minor_pieces <= 1
+            || (minor_pieces == bishops.count_ones()
+                && (bishops & LIGHT_SQUARES == 0 || bishops & !LIGHT_SQUARES == 0))
+    }
+
+    /// Outcome of the game in this position, if it is over. Checkmate, stalemate, insufficient
+    /// material and the seventy-five move and fivefold repetition rules end the game by
+    /// themselves; the fifty move and threefold repetition rules only with `claim_draw`.
+    pub fn get_outcome(&self, history: &PositionHistory, claim_draw: bool) -> Option<GameOutcome> {
+        let draw = |termination| {
+            Some(GameOutcome {
+                termination,
+                winner: None,
+            })
+        };
+
+        if !self.has_legal_moves() {
+            return if self.is_king_in_check(self.to_move) {
+                Some(GameOutcome {
+                    termination: Termination::Checkmate,
+                    winner: Some(self.to_move.opposite()),
+                })
+            } else {
+                draw(Termination::Stalemate)
+            };
+        }
+        if self.is_insufficient_material() {
+            return draw(Termination::InsufficientMaterial);
+        }
+        if self.half_move_clock >= 150 {
+            return draw(Termination::SeventyFiveMoves);
+        }
+
+        let repetitions = history.repetition_count(self);
+        if repetitions >= 5 {
+            return draw(Termination::FivefoldRepetition);
+        }
+        if claim_draw && self.half_move_clock >= 100 {
+            return draw(Termination::FiftyMoves);
+        }
+        if claim_draw && repetitions >= 3 {
+            return draw(Termination::ThreefoldRepetition);
+        }
+        None
+    }
+
+    fn has_legal_moves(&self) -> bool {
+        let mut moves = MoveList::new();
+        generate_legal_moves(self, &mut moves);
+        !moves.is_empty()
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::bitboard::STARTING_POSITION_FEN;
+
+    fn outcome(position_fen: &str, claim_draw: bool) -> Option<GameOutcome> {
+        let bitboard = position_fen.parse::<Bitboard>().unwrap();
+        bitboard.get_outcome(&PositionHistory::new(&bitboard), claim_draw)
+    }
+
+    fn termination(position_fen: &str, claim_draw: bool) -> Option<Termination> {
+        outcome(position_fen, claim_draw).map(|outcome| outcome.termination)
+    }
+
+    #[test]
+    fn test_checkmate_and_stalemate() {
+        let fools_mate = "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3";
+        assert!(fools_mate.parse::<Bitboard>().unwrap().is_checkmate());
+        assert_eq!(
+            outcome(fools_mate, false),
+            Some(GameOutcome {
+                termination: Termination::Checkmate,
+                winner: Some(Color::Black),
+            })
+        );
+        assert_eq!(
+            outcome(fools_mate, false).unwrap().result(),
+            GameResult::BlackWins
+        );
+
+        let stalemate = "7k/5Q2/6K1/8/8/8/8/8 b - - 0 1";
+        assert!(stalemate.parse::<Bitboard>().unwrap().is_stalemate());
+        assert_eq!(termination(stalemate, false), Some(Termination::Stalemate));
+
+        // Mate takes precedence over the seventy-five move rule
+        assert_eq!(
+            termination("7k/6Q1/6K1/8/8/8/8/8 b - - 150 100", false),
+            Some(Termination::Checkmate)
+        );
+        assert_eq!(termination(STARTING_POSITION_FEN, true), None);
+    }
+
+    #[test]
+    fn test_insufficient_material() {
+        for position_fen in [
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4KB2 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4KN2 w - - 0 1",
+            "4k3/8/8/8/8/8/8/3bKB2 w - - 0 1",
+            "4k3/8/8/8/8/8/8/1B1bKB2 w - - 0 1",
+        ] {
+            assert_eq!(
+                termination(position_fen, false),
+                Some(Termination::InsufficientMaterial),
+                "{}",
+                position_fen
+            );
+        }
+
+        for position_fen in [
+            "4k3/8/8/8/8/8/8/2b1KB2 w - - 0 1",
+            "4k3/8/8/8/8/8/8/3nKB2 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4KNN1 w - - 0 1",
+            "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4K2R w - - 0 1",
+        ] {
+            assert!(
+                !position_fen
+                    .parse::<Bitboard>()
+                    .unwrap()
+                    .is_insufficient_material(),
+                "{}",
+                position_fen
+            );
+        }
+    }
+
+    #[test]
+    fn test_move_rules() {
+        let position_fen =
+            |half_move_clock| format!("4k3/8/8/8/8/8/8/R3K3 w - - {} 80", half_move_clock);
+        assert_eq!(termination(&position_fen(99), true), None);
+        assert_eq!(termination(&position_fen(100), false), None);
+        assert_eq!(
+            termination(&position_fen(100), true),
+            Some(Termination::FiftyMoves)
+        );
+        assert_eq!(
+            termination(&position_fen(150), false),
+            Some(Termination::SeventyFiveMoves)
+        );
+    }
+
+    #[test]
+    fn test_repetition() {
+        let mut bitboard = STARTING_POSITION_FEN.parse::<Bitboard>().unwrap();
+        let mut history = PositionHistory::new(&bitboard);
+        let knight_shuffle = ["g1f3", "g8f6", "f3g1", "f6g8"];
+
+        let mut play_shuffles = |count: usize, history: &mut PositionHistory| {
+            for _ in 0..count {
+                for move_str in knight_shuffle {
+                    let _move = bitboard.parse_uci_move(move_str).unwrap();
+                    bitboard.make_move(_move);
+                    history.push(&bitboard);
+                }
+            }
+            bitboard
+        };
+
+        let position = play_shuffles(1, &mut history);
+        assert_eq!(history.repetition_count(&position), 2);
+        assert_eq!(position.get_outcome(&history, true), None);
+
+        let position = play_shuffles(1, &mut history);
+        assert_eq!(history.repetition_count(&position), 3);
+        assert_eq!(position.get_outcome(&history, false), None);
+        assert_eq!(
+            position
+                .get_outcome(&history, true)
+                .map(|outcome| outcome.termination),
+            Some(Termination::ThreefoldRepetition)
+        );
+
+        let position = play_shuffles(2, &mut history);
+        assert_eq!(
+            position.get_outcome(&history, false),
+            Some(GameOutcome {
+                termination: Termination::FivefoldRepetition,
+                winner: None,
+            })
+        );
+
+        // Positions before a pawn move can never repeat
+        let mut position = position;
+        position.make_move(position.parse_uci_move("e2e4").unwrap());
+        history.push(&position);
+        assert_eq!(history.repetition_count(&position), 1);
+
+        // No pawn can take on e3, so the position repeats once the en passant square is gone
+        for move_str in ["g8f6", "g1f3", "f6g8", "f3g1"] {
+            position.make_move(position.parse_uci_move(move_str).unwrap());
+            history.push(&position);
+        }
+        assert_eq!(position.en_passant_square, None);
+        assert_eq!(history.repetition_count(&position), 2);
+    }
+}
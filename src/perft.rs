@@ -0,0 +1,158 @@
+use crate::{
+    bitboard::Bitboard,
+    move_generation::{generate_legal_moves, MoveList},
+    move_performing::perform_move,
+    utils::_move::Move,
+};
+
+/// Count the leaf nodes of the legal move tree `depth` plies below `bitboard`.
+/// Leaves one ply away are counted straight from the move list instead of being played.
+pub fn perft(bitboard: &Bitboard, depth: usize) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+
+    let mut moves = MoveList::new();
+    generate_legal_moves(bitboard, &mut moves);
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+
+    let mut bitboard = *bitboard;
+    moves
+        .into_iter()
+        .map(|_move| {
+            let undo_info = bitboard.make_move(_move);
+            let nodes = perft(&bitboard, depth - 1);
+            bitboard.unmake_move(_move, undo_info);
+            nodes
+        })
+        .sum()
+}
+
+/// Perft split by root move, for narrowing down which move a count mismatch comes from.
+pub fn divide(bitboard: &Bitboard, depth: usize) -> Vec<(Move, u64)> {
+    if depth == 0 {
+        return Vec::new();
+    }
+
+    let mut moves = MoveList::new();
+    generate_legal_moves(bitboard, &mut moves);
+
+    moves
+        .into_iter()
+        .map(|_move| {
+            (
+                _move,
+                perft(&perform_move(bitboard, &_move).unwrap(), depth - 1),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    fn assert_perft(position_fen: &str, expected_counts: &[u64]) {
+        let bitboard = position_fen.parse::<Bitboard>().unwrap();
+        for (depth, &expected) in expected_counts.iter().enumerate() {
+            assert_eq!(
+                perft(&bitboard, depth + 1),
+                expected,
+                "{} at depth {}",
+                position_fen,
+                depth + 1
+            );
+        }
+    }
+
+    #[test]
+    fn test_perft_start_position() {
+        assert_perft(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            &[20, 400, 8902, 197281],
+        );
+    }
+
+    #[test]
+    fn test_perft_kiwipete() {
+        assert_perft(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            &[48, 2039, 97862],
+        );
+    }
+
+    #[test]
+    fn test_perft_position_3() {
+        assert_perft(
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            &[14, 191, 2812, 43238, 674624],
+        );
+    }
+
+    #[test]
+    fn test_perft_position_4() {
+        assert_perft(
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            &[6, 264, 9467, 422333],
+        );
+        // Mirrored
+        assert_perft(
+            "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1",
+            &[6, 264, 9467, 422333],
+        );
+    }
+
+    #[test]
+    fn test_perft_position_5() {
+        assert_perft(
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            &[44, 1486, 62379],
+        );
+    }
+
+    #[test]
+    fn test_perft_position_6() {
+        assert_perft(
+            "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+            &[46, 2079, 89890],
+        );
+    }
+
+    #[test]
+    fn test_perft_chess960() {
+        assert_perft(
+            "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+            &[21, 528, 12189, 326672],
+        );
+        assert_perft(
+            "2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9",
+            &[21, 807, 18002, 667366],
+        );
+        assert_perft(
+            "b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9",
+            &[20, 479, 10471, 273318],
+        );
+        assert_perft(
+            "qbbnnrkr/2pp2pp/p7/1p2pp2/8/P3PP2/1PPP1KPP/QBBNNR1R w hf - 0 9",
+            &[22, 593, 13440, 382958],
+        );
+    }
+
+    #[test]
+    fn test_divide() {
+        let bitboard = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"
+            .parse::<Bitboard>()
+            .unwrap();
+        let counts = divide(&bitboard, 2);
+
+        assert_eq!(counts.len(), 48);
+        assert_eq!(counts.iter().map(|(_, count)| count).sum::<u64>(), 2039);
+        let castle_count = counts
+            .iter()
+            .find(|(_move, _)| _move.to_string() == "e1g1")
+            .map(|&(_, count)| count);
+        assert_eq!(castle_count, Some(43));
+    }
+}
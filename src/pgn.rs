@@ -0,0 +1,187 @@
+pub use self::{reader::PgnReader, writer::PgnExportOptions};
+use crate::{
+    bitboard::{Bitboard, STARTING_POSITION_FEN},
+    utils::{_move::Move, errors::PGNParseError},
+};
+
+use std::fmt;
+use std::str::FromStr;
+
+mod reader;
+mod writer;
+
+/// Tags every PGN game is expected to carry, in their export order.
+pub const SEVEN_TAG_ROSTER: [&str; 7] =
+    ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+    Unknown,
+}
+
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                GameResult::WhiteWins => "1-0",
+                GameResult::BlackWins => "0-1",
+                GameResult::Draw => "1/2-1/2",
+                GameResult::Unknown => "*",
+            }
+        )
+    }
+}
+
+impl FromStr for GameResult {
+    type Err = ();
+
+    fn from_str(result_str: &str) -> Result<GameResult, ()> {
+        match result_str {
+            "1-0" => Ok(GameResult::WhiteWins),
+            "0-1" => Ok(GameResult::BlackWins),
+            "1/2-1/2" => Ok(GameResult::Draw),
+            "*" => Ok(GameResult::Unknown),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A move of a PGN game together with its annotations. `comment_before` is only used for the
+/// first move of the game or of a variation; later comments belong to the preceding move.
+/// Each variation is an alternative to this move, starting from the position before it.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PgnMove {
+    pub _move: Move,
+    pub nags: Vec<u8>,
+    pub comment_before: Option<String>,
+    pub comment: Option<String>,
+    pub variations: Vec<Vec<PgnMove>>,
+}
+
+impl PgnMove {
+    pub fn new(_move: Move) -> PgnMove {
+        PgnMove {
+            _move,
+            nags: Vec::new(),
+            comment_before: None,
+            comment: None,
+            variations: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Game {
+    /// Tag pairs in the order they were read.
+    pub headers: Vec<(String, String)>,
+    pub start_position: Bitboard,
+    pub moves: Vec<PgnMove>,
+    pub result: GameResult,
+}
+
+impl Game {
+    /// An empty game from the standard starting position with the seven tag roster set to
+    /// unknown values.
+    pub fn new() -> Game {
+        let headers = SEVEN_TAG_ROSTER
+            .iter()
+            .map(|&name| {
+                let value = match name {
+                    "Date" => "????.??.??",
+                    "Result" => "*",
+                    _ => "?",
+                };
+                (name.to_string(), value.to_string())
+            })
+            .collect();
+
+        Game {
+            headers,
+            start_position: STARTING_POSITION_FEN.parse::<Bitboard>().unwrap(),
+            moves: Vec::new(),
+            result: GameResult::Unknown,
+        }
+    }
+
+    pub fn get_header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header_name, _)| header_name == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Replace the value of tag `name`, or append it if the game does not have it yet.
+    pub fn set_header(&mut self, name: &str, value: &str) {
+        match self
+            .headers
+            .iter_mut()
+            .find(|(header_name, _)| header_name == name)
+        {
+            Some((_, header_value)) => *header_value = value.to_string(),
+            None => self.headers.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    /// Position reached at the end of the mainline.
+    pub fn final_position(&self) -> Bitboard {
+        let mut bitboard = self.start_position;
+        for pgn_move in &self.moves {
+            bitboard.make_move(pgn_move._move);
+        }
+        bitboard
+    }
+}
+
+impl Default for Game {
+    fn default() -> Game {
+        Game::new()
+    }
+}
+
+/// Parse the first game of a PGN string.
+impl FromStr for Game {
+    type Err = PGNParseError;
+
+    fn from_str(pgn: &str) -> Result<Game, PGNParseError> {
+        PgnReader::new(pgn.as_bytes())
+            .next()
+            .unwrap_or(Err(PGNParseError::NoGame))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn test_headers() {
+        let mut game = Game::new();
+        assert_eq!(game.headers.len(), 7);
+        assert_eq!(game.get_header("Date"), Some("????.??.??"));
+        assert_eq!(game.get_header("ECO"), None);
+
+        game.set_header("White", "Morphy, Paul");
+        game.set_header("ECO", "C41");
+        assert_eq!(game.get_header("White"), Some("Morphy, Paul"));
+        assert_eq!(game.headers.len(), 8);
+        assert_eq!(game.headers[7], (String::from("ECO"), String::from("C41")));
+    }
+
+    #[test]
+    fn test_game_result() {
+        for result in [
+            GameResult::WhiteWins,
+            GameResult::BlackWins,
+            GameResult::Draw,
+            GameResult::Unknown,
+        ] {
+            assert_eq!(result.to_string().parse::<GameResult>(), Ok(result));
+        }
+        assert_eq!("1-1".parse::<GameResult>(), Err(()));
+    }
+}
@@ -0,0 +1,570 @@
+use super::{Game, GameResult, PgnMove};
+use crate::{bitboard::Bitboard, utils::errors::PGNParseError};
+
+use std::io::BufRead;
+
+#[derive(Debug, PartialEq)]
+enum TokenKind {
+    Tag(String, String),
+    Comment(String),
+    VariationStart,
+    VariationEnd,
+    Nag(u8),
+    Move(String),
+    Result(GameResult),
+}
+
+#[derive(Debug)]
+struct Token {
+    kind: TokenKind,
+    text: String,
+    line: usize,
+}
+
+/// Streams games out of a PGN source one at a time, so only the game being parsed is held in
+/// memory. After an error the rest of the broken game is skipped and reading resumes with the
+/// next one.
+pub struct PgnReader<R: BufRead> {
+    reader: R,
+    line: Vec<char>,
+    position: usize,
+    line_number: usize,
+    game_number: usize,
+    peeked: Option<Token>,
+}
+
+impl<R: BufRead> PgnReader<R> {
+    pub fn new(reader: R) -> PgnReader<R> {
+        PgnReader {
+            reader,
+            line: Vec::new(),
+            position: 0,
+            line_number: 0,
+            game_number: 0,
+            peeked: None,
+        }
+    }
+
+    fn read_game(&mut self) -> Result<Option<Game>, PGNParseError> {
+        self.game_number += 1;
+        if self.peek_token()?.is_none() {
+            return Ok(None);
+        }
+
+        let mut game = Game {
+            headers: Vec::new(),
+            ..Game::new()
+        };
+        let mut fen_line = 0;
+        while let Some(Token {
+            kind: TokenKind::Tag(..),
+            ..
+        }) = self.peek_token()?
+        {
+            let token = self.next_token()?.unwrap();
+            if let TokenKind::Tag(name, value) = token.kind {
+                if name == "FEN" {
+                    fen_line = token.line;
+                }
+                game.headers.push((name, value));
+            }
+        }
+
+        if let Some(fen) = game.get_header("FEN") {
+            game.start_position =
+                fen.parse::<Bitboard>()
+                    .map_err(|error| PGNParseError::InvalidFEN {
+                        game: self.game_number,
+                        line: fen_line,
+                        token: fen.to_string(),
+                        error,
+                    })?;
+        }
+
+        let (moves, result) = self.read_moves(game.start_position, 0)?;
+        game.moves = moves;
+        game.result = match result {
+            Some(result) => result,
+            None => game
+                .get_header("Result")
+                .and_then(|result| result.parse::<GameResult>().ok())
+                .unwrap_or(GameResult::Unknown),
+        };
+        Ok(Some(game))
+    }
+
+    /// Read a line of moves played from `bitboard` up to the end of the variation at `depth`, or
+    /// up to the game termination for the mainline.
+    fn read_moves(
+        &mut self,
+        mut bitboard: Bitboard,
+        depth: usize,
+    ) -> Result<(Vec<PgnMove>, Option<GameResult>), PGNParseError> {
+        let mut moves: Vec<PgnMove> = Vec::new();
+        let mut previous_bitboard = bitboard;
+        let mut comment_before: Option<String> = None;
+
+        loop {
+            let token = match self.next_token()? {
+                Some(token) => token,
+                None if depth == 0 => return Ok((moves, None)),
+                None => {
+                    return Err(PGNParseError::UnterminatedVariation {
+                        game: self.game_number,
+                        line: self.line_number,
+                    })
+                }
+            };
+
+            match token.kind {
+                TokenKind::Move(san) => {
+                    let _move =
+                        bitboard
+                            .parse_san(&san)
+                            .map_err(|error| PGNParseError::InvalidMove {
+                                game: self.game_number,
+                                line: token.line,
+                                token: token.text.clone(),
+                                error,
+                            })?;
+                    previous_bitboard = bitboard;
+                    bitboard.make_move(_move);
+
+                    let mut pgn_move = PgnMove::new(_move);
+                    pgn_move.comment_before = comment_before.take();
+                    if let Some(nag) = get_suffix_nag(&token.text) {
+                        pgn_move.nags.push(nag);
+                    }
+                    moves.push(pgn_move);
+                }
+                TokenKind::Comment(comment) => {
+                    let target = match moves.last_mut() {
+                        Some(pgn_move) => &mut pgn_move.comment,
+                        None => &mut comment_before,
+                    };
+                    *target = Some(match target.take() {
+                        Some(existing) => format!("{} {}", existing, comment),
+                        None => comment,
+                    });
+                }
+                TokenKind::Nag(nag) if !moves.is_empty() => {
+                    moves.last_mut().unwrap().nags.push(nag);
+                }
+                TokenKind::VariationStart if !moves.is_empty() => {
+                    let (variation, _) = self.read_moves(previous_bitboard, depth + 1)?;
+                    moves.last_mut().unwrap().variations.push(variation);
+                }
+                TokenKind::VariationEnd if depth > 0 => return Ok((moves, None)),
+                TokenKind::Result(result) if depth == 0 => return Ok((moves, Some(result))),
+                // A tag pair without a preceding result starts the next game.
+                TokenKind::Tag(..) if depth == 0 => {
+                    self.peeked = Some(token);
+                    return Ok((moves, None));
+                }
+                _ => {
+                    return Err(PGNParseError::UnexpectedToken {
+                        game: self.game_number,
+                        line: token.line,
+                        token: token.text,
+                    })
+                }
+            }
+        }
+    }
+
+    /// Drop the remaining tokens of a game that failed to parse.
+    fn skip_game(&mut self) {
+        let mut in_movetext = false;
+        loop {
+            match self.next_token() {
+                Ok(None) => return,
+                Ok(Some(token)) => match token.kind {
+                    TokenKind::Result(_) => return,
+                    TokenKind::Tag(..) if in_movetext => {
+                        self.peeked = Some(token);
+                        return;
+                    }
+                    TokenKind::Tag(..) => {}
+                    _ => in_movetext = true,
+                },
+                Err(PGNParseError::Io(_)) => return,
+                Err(_) => in_movetext = true,
+            }
+        }
+    }
+
+    fn peek_token(&mut self) -> Result<Option<&Token>, PGNParseError> {
+        if self.peeked.is_none() {
+            self.peeked = self.next_token()?;
+        }
+        Ok(self.peeked.as_ref())
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token>, PGNParseError> {
+        if let Some(token) = self.peeked.take() {
+            return Ok(Some(token));
+        }
+
+        loop {
+            let c = match self.peek_char()? {
+                Some(c) => c,
+                None => return Ok(None),
+            };
+            let line = self.line_number;
+            self.position += 1;
+
+            let kind = match c {
+                c if c.is_whitespace() || c == '\u{feff}' => continue,
+                '[' => return self.read_tag(line).map(Some),
+                '{' => {
+                    let mut comment = String::new();
+                    loop {
+                        match self.next_char()? {
+                            Some('}') => break,
+                            Some(c) => comment.push(c),
+                            None => {
+                                return Err(PGNParseError::UnterminatedComment {
+                                    game: self.game_number,
+                                    line,
+                                })
+                            }
+                        }
+                    }
+                    TokenKind::Comment(normalize_whitespace(&comment))
+                }
+                ';' => {
+                    let comment: String = self.line[self.position..].iter().collect();
+                    self.position = self.line.len();
+                    TokenKind::Comment(comment.trim().to_string())
+                }
+                '(' => TokenKind::VariationStart,
+                ')' => TokenKind::VariationEnd,
+                _ => {
+                    let mut text = c.to_string();
+                    while let Some(&c) = self.line.get(self.position) {
+                        if c.is_whitespace() || "[]{}();$".contains(c) {
+                            break;
+                        }
+                        text.push(c);
+                        self.position += 1;
+                    }
+
+                    if c == '$' {
+                        match text[1..].parse::<u8>() {
+                            Ok(nag) => TokenKind::Nag(nag),
+                            Err(_) => {
+                                return Err(PGNParseError::UnexpectedToken {
+                                    game: self.game_number,
+                                    line,
+                                    token: text,
+                                })
+                            }
+                        }
+                    } else if let Ok(result) = text.parse::<GameResult>() {
+                        TokenKind::Result(result)
+                    } else {
+                        // Move numbers such as `12.` or `12...` may be glued to the move.
+                        let san = match text.find('.') {
+                            Some(index) if text[..index].chars().all(|c| c.is_ascii_digit()) => {
+                                text[index..].trim_start_matches('.').to_string()
+                            }
+                            _ => text.clone(),
+                        };
+                        if san.is_empty() || san == "e.p." {
+                            continue;
+                        }
+                        return Ok(Some(Token {
+                            kind: TokenKind::Move(san.clone()),
+                            text: san,
+                            line,
+                        }));
+                    }
+                }
+            };
+
+            let text = match &kind {
+                TokenKind::Comment(comment) => format!("{{{}}}", comment),
+                TokenKind::VariationStart => String::from("("),
+                TokenKind::VariationEnd => String::from(")"),
+                TokenKind::Nag(nag) => format!("${}", nag),
+                TokenKind::Result(result) => result.to_string(),
+                _ => String::new(),
+            };
+            return Ok(Some(Token { kind, text, line }));
+        }
+    }
+
+    /// Read a `[Name "value"]` tag pair whose opening bracket has just been consumed. Tag pairs
+    /// never span lines.
+    fn read_tag(&mut self, line: usize) -> Result<Token, PGNParseError> {
+        let mut raw = String::from("[");
+        let mut in_string = false;
+        let mut escaped = false;
+        while let Some(&c) = self.line.get(self.position) {
+            if c == '\n' || c == '\r' {
+                break;
+            }
+            raw.push(c);
+            self.position += 1;
+            match c {
+                '\\' if in_string && !escaped => {
+                    escaped = true;
+                    continue;
+                }
+                '"' if !escaped => in_string = !in_string,
+                ']' if !in_string => break,
+                _ => {}
+            }
+            escaped = false;
+        }
+
+        let malformed = || PGNParseError::MalformedTag {
+            game: self.game_number,
+            line,
+            token: raw.clone(),
+        };
+
+        let inner = raw
+            .strip_prefix('[')
+            .and_then(|inner| inner.strip_suffix(']'))
+            .ok_or_else(malformed)?
+            .trim();
+        let name_length = inner
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .ok_or_else(malformed)?;
+        let name = &inner[..name_length];
+        let quoted = inner[name_length..].trim();
+        if name.is_empty() || quoted.len() < 2 || !quoted.starts_with('"') || !quoted.ends_with('"')
+        {
+            return Err(malformed());
+        }
+
+        let mut value = String::new();
+        let mut chars = quoted[1..quoted.len() - 1].chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => value.extend(chars.next()),
+                _ => value.push(c),
+            }
+        }
+
+        Ok(Token {
+            kind: TokenKind::Tag(name.to_string(), value),
+            text: raw.clone(),
+            line,
+        })
+    }
+
+    fn peek_char(&mut self) -> Result<Option<char>, PGNParseError> {
+        while self.position >= self.line.len() {
+            let mut line = String::new();
+            let bytes_read = self
+                .reader
+                .read_line(&mut line)
+                .map_err(|error| PGNParseError::Io(error.to_string()))?;
+            if bytes_read == 0 {
+                return Ok(None);
+            }
+            self.line_number += 1;
+            self.position = 0;
+            // Lines starting with `%` are escaped from PGN processing.
+            self.line = if line.starts_with('%') {
+                Vec::new()
+            } else {
+                line.chars().collect()
+            };
+        }
+        Ok(Some(self.line[self.position]))
+    }
+
+    fn next_char(&mut self) -> Result<Option<char>, PGNParseError> {
+        let c = self.peek_char()?;
+        if c.is_some() {
+            self.position += 1;
+        }
+        Ok(c)
+    }
+}
+
+impl<R: BufRead> Iterator for PgnReader<R> {
+    type Item = Result<Game, PGNParseError>;
+
+    fn next(&mut self) -> Option<Result<Game, PGNParseError>> {
+        match self.read_game() {
+            Ok(game) => game.map(Ok),
+            Err(error) => {
+                self.skip_game();
+                Some(Err(error))
+            }
+        }
+    }
+}
+
+/// Traditional move suffix annotations and their numeric annotation glyphs.
+fn get_suffix_nag(san: &str) -> Option<u8> {
+    let suffix_start = san
+        .rfind(|c| c != '!' && c != '?')
+        .map_or(0, |index| index + 1);
+    match &san[suffix_start..] {
+        "!" => Some(1),
+        "?" => Some(2),
+        "!!" => Some(3),
+        "??" => Some(4),
+        "!?" => Some(5),
+        "?!" => Some(6),
+        _ => None,
+    }
+}
+
+fn normalize_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::bitboard::STARTING_POSITION_FEN;
+
+    const TWO_GAMES: &str = r#"[Event "F/S Return Match"]
+[Site "Belgrade, Serbia JUG"]
+[Date "1992.11.04"]
+[Round "29"]
+[White "Fischer, Robert J."]
+[Black "Spassky, Boris V."]
+[Result "1/2-1/2"]
+
+1. e4 e5 2. Nf3 Nc6 3. Bb5 {This opening is called the Ruy Lopez.} 3... a6
+4. Ba4 Nf6 5. O-O Be7 6. Re1 b5 7. Bb3 d6 8. c3 O-O 9. h3 Nb8 10. d4 Nbd7
+11. c4 c6 12. cxb5 axb5 13. Nc3 Bb7 14. Bg5 b4 15. Nb1 h6 16. Bh4 c5 17. dxe5
+Nxe4 18. Bxe7 Qxe7 19. exd6 Qf6 20. Nbd2 Nxd6 21. Nc4 Nxc4 22. Bxc4 Nb6
+23. Ne5 Rae8 24. Bxf7+ Rxf7 25. Nxf7 Rxe1+ 26. Qxe1 Kxf7 27. Qe3 Qg5 28. Qxg5
+hxg5 29. b3 Ke6 30. a3 Kd6 31. axb4 cxb4 32. Ra5 Nd5 33. f3 Bc8 34. Kf2 Bf5
+35. Ra7 g6 36. Ra6+ Kc5 37. Ke1 Nf4 38. g3 Nxh3 39. Kd2 Kb5 40. Rd6 Kc5 41. Ra6
+Nf2 42. g4 Bd3 43. Re6 1/2-1/2
+
+[Event "Annotated"]
+[Site "?"]
+[Result "1-0"]
+[Annotator "Someone \"quoted\""]
+
+{Start} 1.e4! $14 e5 (1...c5 2.Nf3 (2.Nc3) d6) 2.Qh5?! Nc6 ; rest of line
+% escaped line e4 e5
+3.Bc4 Nf6?? 4.Qxf7# 1-0
+"#;
+
+    fn read_all(pgn: &str) -> Vec<Result<Game, PGNParseError>> {
+        PgnReader::new(pgn.as_bytes()).collect()
+    }
+
+    #[test]
+    fn test_read_games() {
+        let games = read_all(TWO_GAMES);
+        assert_eq!(games.len(), 2);
+
+        let game = games[0].as_ref().unwrap();
+        assert_eq!(game.headers.len(), 7);
+        assert_eq!(game.get_header("White"), Some("Fischer, Robert J."));
+        assert_eq!(game.moves.len(), 85);
+        assert_eq!(game.result, GameResult::Draw);
+        assert_eq!(
+            game.moves[4].comment.as_deref(),
+            Some("This opening is called the Ruy Lopez.")
+        );
+        assert_eq!(
+            game.final_position().to_str(),
+            "8/8/4R1p1/2k3p1/1p4P1/1P1b1P2/3K1n2/8 b - - 2 43"
+        );
+
+        let game = games[1].as_ref().unwrap();
+        assert_eq!(game.get_header("Annotator"), Some("Someone \"quoted\""));
+        assert_eq!(game.start_position, STARTING_POSITION_FEN.parse().unwrap());
+        assert_eq!(game.result, GameResult::WhiteWins);
+        assert_eq!(game.moves.len(), 7);
+        assert_eq!(game.moves[0].comment_before.as_deref(), Some("Start"));
+        assert_eq!(game.moves[0].nags, vec![1, 14]);
+        assert_eq!(game.moves[2].nags, vec![6]);
+        assert_eq!(game.moves[3].comment.as_deref(), Some("rest of line"));
+        assert_eq!(game.moves[5].nags, vec![4]);
+
+        let variations = &game.moves[1].variations;
+        assert_eq!(variations.len(), 1);
+        assert_eq!(variations[0].len(), 3);
+        assert_eq!(variations[0][0]._move.to_string(), "c7c5");
+        assert_eq!(variations[0][1].variations[0][0]._move.to_string(), "b1c3");
+    }
+
+    #[test]
+    fn test_set_up_position() {
+        let game = r#"[FEN "4k3/8/8/8/8/8/4P3/4K3 b - - 0 1"]
+[SetUp "1"]
+
+1... Kd7 2. e4 *"#
+            .parse::<Game>()
+            .unwrap();
+        assert_eq!(game.moves.len(), 2);
+        assert_eq!(game.result, GameResult::Unknown);
+        assert_eq!(
+            game.final_position().to_str(),
+            "8/3k4/8/8/4P3/8/8/4K3 b - e3 0 2"
+        );
+    }
+
+    #[test]
+    fn test_missing_result_and_headers() {
+        let games = read_all("1. d4 d5\n\n[Event \"Next\"]\n[Result \"0-1\"]\n\n1. c4\n");
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].as_ref().unwrap().moves.len(), 2);
+        assert!(games[0].as_ref().unwrap().headers.is_empty());
+
+        let game = games[1].as_ref().unwrap();
+        assert_eq!(game.moves.len(), 1);
+        assert_eq!(game.result, GameResult::BlackWins);
+    }
+
+    #[test]
+    fn test_errors() {
+        let games = read_all(
+            "[Event \"One\"]\n\n1. e4 e5 2. Ke3 *\n\n[Event \"Two\"\n\n1. e4 *\n\n[Event \"Three\"]\n\n1. e4 { open *\n",
+        );
+        assert_eq!(games.len(), 3);
+        assert!(matches!(
+            &games[0],
+            Err(PGNParseError::InvalidMove { game: 1, line: 3, token, .. }) if token == "Ke3"
+        ));
+        assert_eq!(
+            games[1],
+            Err(PGNParseError::MalformedTag {
+                game: 2,
+                line: 5,
+                token: String::from("[Event \"Two\""),
+            })
+        );
+        assert_eq!(
+            games[2],
+            Err(PGNParseError::UnterminatedComment { game: 3, line: 11 })
+        );
+
+        assert_eq!(
+            "1. e4 (1. d4) )".parse::<Game>(),
+            Err(PGNParseError::UnexpectedToken {
+                game: 1,
+                line: 1,
+                token: String::from(")"),
+            })
+        );
+        assert_eq!(
+            "1. e4 (1. d4 *".parse::<Game>(),
+            Err(PGNParseError::UnexpectedToken {
+                game: 1,
+                line: 1,
+                token: String::from("*"),
+            })
+        );
+        assert_eq!(
+            "1. e4 (1. d4".parse::<Game>(),
+            Err(PGNParseError::UnterminatedVariation { game: 1, line: 1 })
+        );
+        assert_eq!("".parse::<Game>(), Err(PGNParseError::NoGame));
+        assert_eq!(" \n\n".parse::<Game>(), Err(PGNParseError::NoGame));
+    }
+}
@@ -0,0 +1,244 @@
+use super::{Game, PgnMove, SEVEN_TAG_ROSTER};
+use crate::{
+    bitboard::{Bitboard, STARTING_POSITION_FEN},
+    utils::enums::Color,
+};
+
+use std::fmt;
+
+/// What `Game::to_pgn` writes besides the mainline.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct PgnExportOptions {
+    pub comments: bool,
+    pub variations: bool,
+    /// Maximum movetext line length; 80 per the PGN export format.
+    pub line_width: usize,
+}
+
+impl Default for PgnExportOptions {
+    fn default() -> PgnExportOptions {
+        PgnExportOptions {
+            comments: true,
+            variations: true,
+            line_width: 80,
+        }
+    }
+}
+
+impl Game {
+    /// Export the game in PGN: the seven tag roster first, then `SetUp`/`FEN` for a non-standard
+    /// start position, the remaining tags, and the SAN movetext wrapped at `line_width` columns.
+    pub fn to_pgn(&self, options: &PgnExportOptions) -> String {
+        let mut pgn = String::new();
+
+        let result = self.result.to_string();
+        for name in SEVEN_TAG_ROSTER {
+            let value = match name {
+                "Result" => result.as_str(),
+                _ => self.get_header(name).unwrap_or(match name {
+                    "Date" => "????.??.??",
+                    _ => "?",
+                }),
+            };
+            push_tag(&mut pgn, name, value);
+        }
+        if self.start_position != STARTING_POSITION_FEN.parse::<Bitboard>().unwrap() {
+            push_tag(&mut pgn, "SetUp", "1");
+            push_tag(&mut pgn, "FEN", &self.start_position.to_str());
+        }
+        for (name, value) in &self.headers {
+            if !SEVEN_TAG_ROSTER.contains(&name.as_str()) && name != "SetUp" && name != "FEN" {
+                push_tag(&mut pgn, name, value);
+            }
+        }
+        pgn.push('\n');
+
+        let mut tokens = Vec::new();
+        push_moves(&mut tokens, self.start_position, &self.moves, options);
+        tokens.push(result);
+
+        let mut line = String::new();
+        for token in tokens {
+            if !line.is_empty() && line.len() + 1 + token.len() > options.line_width {
+                pgn.push_str(&line);
+                pgn.push('\n');
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&token);
+        }
+        pgn.push_str(&line);
+        pgn.push('\n');
+
+        pgn
+    }
+}
+
+impl fmt::Display for Game {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_pgn(&PgnExportOptions::default()))
+    }
+}
+
+fn push_tag(pgn: &mut String, name: &str, value: &str) {
+    let escaped = value.replace('\\', "\\\\").replace('"', "\\\"");
+    pgn.push_str(&format!("[{} \"{}\"]\n", name, escaped));
+}
+
+/// Append the movetext tokens of `moves` played from `bitboard`. Black moves get a `N...` move
+/// number at the start of a line of play and after a comment or variation interrupts it.
+fn push_moves(
+    tokens: &mut Vec<String>,
+    mut bitboard: Bitboard,
+    moves: &[PgnMove],
+    options: &PgnExportOptions,
+) {
+    let mut needs_number = true;
+    for pgn_move in moves {
+        if options.comments {
+            if let Some(comment) = &pgn_move.comment_before {
+                push_comment(tokens, comment);
+                needs_number = true;
+            }
+        }
+
+        let san = bitboard.to_san(&pgn_move._move);
+        match bitboard.to_move {
+            Color::White => tokens.push(format!("{}.", bitboard.full_move_clock)),
+            Color::Black if needs_number => tokens.push(format!("{}...", bitboard.full_move_clock)),
+            Color::Black => {}
+        }
+        tokens.push(san);
+        needs_number = false;
+
+        for nag in &pgn_move.nags {
+            tokens.push(format!("${}", nag));
+        }
+        if options.comments {
+            if let Some(comment) = &pgn_move.comment {
+                push_comment(tokens, comment);
+                needs_number = true;
+            }
+        }
+        if options.variations {
+            for variation in &pgn_move.variations {
+                let start = tokens.len();
+                push_moves(tokens, bitboard, variation, options);
+                if tokens.len() == start {
+                    continue;
+                }
+                tokens[start].insert(0, '(');
+                tokens.last_mut().unwrap().push(')');
+                needs_number = true;
+            }
+        }
+
+        bitboard.make_move(pgn_move._move);
+    }
+}
+
+/// Comments are split into words so long ones wrap like the rest of the movetext.
+fn push_comment(tokens: &mut Vec<String>, comment: &str) {
+    let start = tokens.len();
+    tokens.extend(comment.split_whitespace().map(String::from));
+    if tokens.len() == start {
+        tokens.push(String::from("{}"));
+        return;
+    }
+    tokens[start].insert(0, '{');
+    tokens.last_mut().unwrap().push('}');
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::pgn::{GameResult, PgnReader};
+
+    const ANNOTATED_GAME: &str = r#"[Event "Annotated"]
+[Site "?"]
+[Date "????.??.??"]
+[Round "?"]
+[White "?"]
+[Black "?"]
+[Result "1-0"]
+[Annotator "Someone \"quoted\""]
+
+{Start} 1. e4 $1 $14 e5 (1... c5 2. Nf3 (2. Nc3) 2... d6) 2. Qh5 $6 Nc6 {Good}
+3. Bc4 Nf6 $4 4. Qxf7# 1-0
+"#;
+
+    #[test]
+    fn test_round_trip() {
+        let game = ANNOTATED_GAME.parse::<Game>().unwrap();
+        assert_eq!(game.to_string(), ANNOTATED_GAME);
+        assert_eq!(game.to_string().parse::<Game>(), Ok(game));
+    }
+
+    #[test]
+    fn test_export_options() {
+        let game = ANNOTATED_GAME.parse::<Game>().unwrap();
+        let options = PgnExportOptions {
+            comments: false,
+            variations: false,
+            ..PgnExportOptions::default()
+        };
+        let pgn = game.to_pgn(&options);
+        assert!(pgn.ends_with("\n\n1. e4 $1 $14 e5 2. Qh5 $6 Nc6 3. Bc4 Nf6 $4 4. Qxf7# 1-0\n"));
+    }
+
+    #[test]
+    fn test_set_up_position() {
+        let mut game = Game::new();
+        game.start_position = "4k3/8/8/8/8/8/4P3/4K3 b - - 0 1".parse().unwrap();
+        game.set_header("ECO", "A00");
+        let _move = game.start_position.parse_san("Kd7").unwrap();
+        game.moves.push(PgnMove::new(_move));
+        game.result = GameResult::Draw;
+
+        assert_eq!(
+            game.to_string(),
+            r#"[Event "?"]
+[Site "?"]
+[Date "????.??.??"]
+[Round "?"]
+[White "?"]
+[Black "?"]
+[Result "1/2-1/2"]
+[SetUp "1"]
+[FEN "4k3/8/8/8/8/8/4P3/4K3 b - - 0 1"]
+[ECO "A00"]
+
+1... Kd7 1/2-1/2
+"#
+        );
+        let read_game = game.to_string().parse::<Game>().unwrap();
+        assert_eq!(read_game.start_position, game.start_position);
+        assert_eq!(read_game.moves, game.moves);
+        assert_eq!(read_game.result, game.result);
+    }
+
+    #[test]
+    fn test_line_wrapping() {
+        let pgn = "1. e4 e5 2. Nf3 Nc6 3. Bb5 {This opening is called the Ruy Lopez.} 3... a6
+4. Ba4 Nf6 5. O-O Be7 6. Re1 b5 7. Bb3 d6 8. c3 O-O 9. h3 Nb8 10. d4 Nbd7
+11. c4 c6 12. cxb5 axb5 13. Nc3 Bb7 14. Bg5 b4 15. Nb1 h6 16. Bh4 c5 *";
+        let game = pgn.parse::<Game>().unwrap();
+
+        for line_width in [80, 40] {
+            let options = PgnExportOptions {
+                line_width,
+                ..PgnExportOptions::default()
+            };
+            let exported = game.to_pgn(&options);
+            let movetext = exported.split("\n\n").nth(1).unwrap();
+            assert!(movetext.lines().all(|line| line.len() <= line_width));
+            assert!(movetext.lines().any(|line| line.len() > line_width - 8));
+
+            let games: Vec<_> = PgnReader::new(exported.as_bytes()).collect();
+            assert_eq!(games.len(), 1);
+            assert_eq!(games[0].as_ref().unwrap().moves, game.moves);
+        }
+    }
+}
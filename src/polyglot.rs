@@ -0,0 +1,161 @@
+use self::random::RANDOM64;
+pub use self::{
+    builder::{BookBuilder, BookBuilderOptions},
+    reader::{PolyglotBook, PolyglotEntry},
+};
+use crate::{
+    bitboard::Bitboard,
+    utils::{
+        _move::Move,
+        enums::{CastleMoves, Color, File, Piece, Square},
+    },
+};
+
+use int_enum::IntEnum;
+use strum::IntoEnumIterator;
+
+mod builder;
+mod random;
+mod reader;
+
+const CASTLING_OFFSET: usize = 768;
+const EN_PASSANT_OFFSET: usize = 772;
+const WHITE_TO_MOVE_OFFSET: usize = 780;
+
+impl Bitboard {
+    /// Key of the position in Polyglot books. Like `get_zobrist_key`, the en passant file only
+    /// counts when a pawn of the side to move stands ready to capture, whether or not the capture
+    /// is legal.
+    pub fn get_polyglot_key(&self) -> u64 {
+        let mut key = 0;
+        for color in Color::iter() {
+            for piece in Piece::iter() {
+                let kind = 2 * piece as usize + (color == Color::White) as usize;
+                for square in self.get_piece(color, piece).iter() {
+                    key ^= RANDOM64[64 * kind + square as usize];
+                }
+            }
+        }
+
+        for cm in CastleMoves::iter().filter(|&cm| self.has_castling_right(cm)) {
+            key ^= RANDOM64[CASTLING_OFFSET + cm.index()];
+        }
+
+        if let Some(square) = self.get_capturable_en_passant_square() {
+            key ^= RANDOM64[EN_PASSANT_OFFSET + square as usize % 8];
+        }
+
+        if self.to_move == Color::White {
+            key ^= RANDOM64[WHITE_TO_MOVE_OFFSET];
+        }
+        key
+    }
+}
+
+/// Turn a Polyglot move into a `Move` of `bitboard`. Polyglot stores castling as the king
+/// capturing its own rook, which outside Chess960 mode becomes the king's two square move.
+pub fn decode_move(bitboard: &Bitboard, raw_move: u16) -> Move {
+    let mut dest = Square::from_int((raw_move & 0x3F) as u8).unwrap();
+    let orig = Square::from_int(((raw_move >> 6) & 0x3F) as u8).unwrap();
+    let promotion = match (raw_move >> 12) & 0x7 {
+        1 => Some(Piece::Knight),
+        2 => Some(Piece::Bishop),
+        3 => Some(Piece::Rook),
+        4 => Some(Piece::Queen),
+        _ => None,
+    };
+
+    let us = bitboard.to_move;
+    if !bitboard.chess960
+        && bitboard.get_piece_at(orig) == Some((us, Piece::King))
+        && bitboard.get_piece_at(dest) == Some((us, Piece::Rook))
+    {
+        let king_file = if dest as u8 > orig as u8 {
+            File::GFile
+        } else {
+            File::CFile
+        };
+        dest = Square::from_int((orig as u8 & !7) + king_file as u8).unwrap();
+    }
+
+    Move {
+        orig,
+        dest,
+        promotion,
+    }
+}
+
+/// Inverse of `decode_move`.
+pub fn encode_move(bitboard: &Bitboard, _move: &Move) -> u16 {
+    let dest = match bitboard.get_castle(_move) {
+        Some(cm) => bitboard.get_castling_rook_square(cm),
+        None => _move.dest,
+    };
+    let promotion = match _move.promotion {
+        Some(Piece::Knight) => 1,
+        Some(Piece::Bishop) => 2,
+        Some(Piece::Rook) => 3,
+        Some(Piece::Queen) => 4,
+        _ => 0,
+    };
+    dest as u16 | (_move.orig as u16) << 6 | promotion << 12
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::bitboard::STARTING_POSITION_FEN;
+
+    #[test]
+    fn test_polyglot_key() {
+        // Test positions from the Polyglot book format specification
+        let mut bitboard = STARTING_POSITION_FEN.parse::<Bitboard>().unwrap();
+        assert_eq!(bitboard.get_polyglot_key(), 0x463B96181691FC9C);
+        for (move_str, expected_key) in [
+            ("e2e4", 0x823C9B50FD114196),
+            ("d7d5", 0x0756B94461C50FB0),
+            ("e4e5", 0x662FAFB965DB29D4),
+            ("f7f5", 0x22A48B5A8E47FF78),
+            ("e1e2", 0x652A607CA3F242C1),
+            ("e8f7", 0x00FDD303C946BDD9),
+        ] {
+            bitboard.make_move(bitboard.parse_uci_move(move_str).unwrap());
+            assert_eq!(bitboard.get_polyglot_key(), expected_key, "{}", move_str);
+        }
+
+        let mut bitboard = STARTING_POSITION_FEN.parse::<Bitboard>().unwrap();
+        for move_str in ["a2a4", "b7b5", "h2h4", "b5b4", "c2c4"] {
+            bitboard.make_move(bitboard.parse_uci_move(move_str).unwrap());
+        }
+        assert_eq!(bitboard.get_polyglot_key(), 0x3C8123EA7B067637);
+        for move_str in ["b4c3", "a1a3"] {
+            bitboard.make_move(bitboard.parse_uci_move(move_str).unwrap());
+        }
+        assert_eq!(bitboard.get_polyglot_key(), 0x5C3F9B829B279560);
+    }
+
+    #[test]
+    fn test_move_encoding() {
+        let bitboard = "r3k2r/pPpppppp/8/8/8/8/PPPPPPPP/R3K2R w KQkq - 0 1"
+            .parse::<Bitboard>()
+            .unwrap();
+        for (move_str, raw_move) in [
+            ("e2e4", 0x031C),
+            ("b7a8q", 0x4C78),
+            ("b7b8n", 0x1C79),
+            // Castling is stored as the king taking the rook
+            ("e1g1", 0x0107),
+            ("e1c1", 0x0100),
+        ] {
+            let _move = move_str.parse::<Move>().unwrap();
+            assert_eq!(encode_move(&bitboard, &_move), raw_move, "{}", move_str);
+            assert_eq!(decode_move(&bitboard, raw_move), _move, "{}", move_str);
+        }
+
+        let mut bitboard = bitboard;
+        bitboard.chess960 = true;
+        let castle = "e1h1".parse::<Move>().unwrap();
+        assert_eq!(encode_move(&bitboard, &castle), 0x0107);
+        assert_eq!(decode_move(&bitboard, 0x0107), castle);
+    }
+}
@@ -0,0 +1,203 @@
+use super::{encode_move, PolyglotBook, PolyglotEntry};
+use crate::{
+    move_performing::perform_move,
+    pgn::{Game, GameResult},
+    utils::{enums::Color, errors::PerformMoveError},
+};
+
+use std::collections::HashMap;
+
+/// What `BookBuilder` puts in the book.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct BookBuilderOptions {
+    /// Only the first `max_ply` half moves of each game are added.
+    pub max_ply: usize,
+    /// Moves played in fewer games are left out.
+    pub min_games: u32,
+    /// Weight moves by the points they scored for the side playing them, counting two per win
+    /// and one per draw, instead of by how often they were played. Moves that never scored are
+    /// left out.
+    pub win_rate_weighting: bool,
+}
+
+impl Default for BookBuilderOptions {
+    fn default() -> BookBuilderOptions {
+        BookBuilderOptions {
+            max_ply: 30,
+            min_games: 3,
+            win_rate_weighting: true,
+        }
+    }
+}
+
+/// Results of the games in which a move was played, from the side playing it.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+struct MoveStats {
+    games: u32,
+    wins: u32,
+    draws: u32,
+}
+
+/// Collects move statistics from games and turns them into a Polyglot book.
+#[derive(Debug, Clone, Default)]
+pub struct BookBuilder {
+    options: BookBuilderOptions,
+    stats: HashMap<(u64, u16), MoveStats>,
+}
+
+impl BookBuilder {
+    pub fn new(options: BookBuilderOptions) -> BookBuilder {
+        BookBuilder {
+            options,
+            stats: HashMap::new(),
+        }
+    }
+
+    /// Add the mainline of `game`, up to `max_ply` half moves. Games without a result count
+    /// towards how often their moves were played but score nothing.
+    pub fn add_game(&mut self, game: &Game) -> Result<(), PerformMoveError> {
+        let winner = match game.result {
+            GameResult::WhiteWins => Some(Color::White),
+            GameResult::BlackWins => Some(Color::Black),
+            _ => None,
+        };
+
+        let mut bitboard = game.start_position;
+        for pgn_move in game.moves.iter().take(self.options.max_ply) {
+            let key = bitboard.get_polyglot_key();
+            let raw_move = encode_move(&bitboard, &pgn_move._move);
+            let stats = self.stats.entry((key, raw_move)).or_default();
+            stats.games += 1;
+            if winner == Some(bitboard.to_move) {
+                stats.wins += 1;
+            } else if game.result == GameResult::Draw {
+                stats.draws += 1;
+            }
+
+            bitboard = perform_move(&bitboard, &pgn_move._move)?;
+        }
+        Ok(())
+    }
+
+    /// The book of every move played in at least `min_games` games. Weights are scaled down per
+    /// position when they do not fit in 16 bits, and the moves of a position are listed from
+    /// the heaviest.
+    pub fn build(&self) -> PolyglotBook {
+        let mut positions: HashMap<u64, Vec<(u16, u64)>> = HashMap::new();
+        for (&(key, raw_move), stats) in &self.stats {
+            if stats.games < self.options.min_games {
+                continue;
+            }
+            let weight = if self.options.win_rate_weighting {
+                2 * stats.wins as u64 + stats.draws as u64
+            } else {
+                stats.games as u64
+            };
+            if weight > 0 {
+                positions.entry(key).or_default().push((raw_move, weight));
+            }
+        }
+
+        let mut entries = Vec::new();
+        for (key, mut moves) in positions {
+            moves.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+            let max_weight = moves[0].1;
+            let scale = max_weight.div_ceil(u16::MAX as u64);
+            entries.extend(moves.into_iter().map(|(raw_move, weight)| PolyglotEntry {
+                key,
+                raw_move,
+                weight: (weight / scale).max(1) as u16,
+                learn: 0,
+            }));
+        }
+        PolyglotBook::from_entries(entries)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::{
+        bitboard::{Bitboard, STARTING_POSITION_FEN},
+        pgn::PgnReader,
+        utils::_move::Move,
+    };
+
+    const GAMES: &str = "1. e4 e5 2. Nf3 Nc6 1-0
+
+1. e4 c5 2. Nf3 d6 1-0
+
+1. e4 e5 2. Nf3 Nf6 0-1
+
+1. d4 d5 2. c4 e6 1/2-1/2
+
+1. d4 Nf6 2. c4 e6 1/2-1/2
+
+1. d4 d5 2. Nf3 Nf6 *
+";
+
+    fn build(options: BookBuilderOptions) -> PolyglotBook {
+        let mut builder = BookBuilder::new(options);
+        for game in PgnReader::new(GAMES.as_bytes()) {
+            builder.add_game(&game.unwrap()).unwrap();
+        }
+        builder.build()
+    }
+
+    fn book_moves(book: &PolyglotBook, moves: &[&str]) -> Vec<(Move, u16)> {
+        let mut bitboard = STARTING_POSITION_FEN.parse::<Bitboard>().unwrap();
+        for move_str in moves {
+            bitboard.make_move(move_str.parse().unwrap());
+        }
+        book.get_moves(&bitboard)
+    }
+
+    #[test]
+    fn test_build_book() {
+        let book = build(BookBuilderOptions {
+            max_ply: 2,
+            min_games: 1,
+            win_rate_weighting: true,
+        });
+        // e4 scored two wins, d4 two draws and an unfinished game
+        assert_eq!(
+            book_moves(&book, &[]),
+            vec![("e2e4".parse().unwrap(), 4), ("d2d4".parse().unwrap(), 2)]
+        );
+        // 1... e5 won once and lost once, 1... c5 never scored
+        assert_eq!(
+            book_moves(&book, &["e2e4"]),
+            vec![("e7e5".parse().unwrap(), 2)]
+        );
+        // Beyond the maximum ply
+        assert_eq!(book_moves(&book, &["e2e4", "e7e5"]), vec![]);
+
+        let book = build(BookBuilderOptions {
+            max_ply: 4,
+            min_games: 2,
+            win_rate_weighting: false,
+        });
+        assert_eq!(
+            book_moves(&book, &[]),
+            vec![("d2d4".parse().unwrap(), 3), ("e2e4".parse().unwrap(), 3)]
+        );
+        assert_eq!(
+            book_moves(&book, &["e2e4", "e7e5"]),
+            vec![("g1f3".parse().unwrap(), 2)]
+        );
+        // Played only once
+        assert_eq!(book_moves(&book, &["d2d4", "g8f6"]), vec![]);
+    }
+
+    #[test]
+    fn test_write_and_read_book() {
+        let book = build(BookBuilderOptions::default());
+        let bytes = book.to_bytes();
+        assert_eq!(bytes.len(), 16 * book.entries().len());
+        assert!(book
+            .entries()
+            .windows(2)
+            .all(|pair| pair[0].key <= pair[1].key));
+        assert_eq!(PolyglotBook::from_bytes(&bytes), Ok(book));
+    }
+}
@@ -0,0 +1,225 @@
+use super::decode_move;
+use crate::{
+    bitboard::Bitboard,
+    move_generation::{generate_legal_moves, MoveList},
+    utils::{_move::Move, errors::PolyglotError},
+};
+
+use std::{fs, path::Path};
+
+const ENTRY_SIZE: usize = 16;
+
+/// One record of a Polyglot book, stored big-endian in 16 bytes.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct PolyglotEntry {
+    pub key: u64,
+    pub raw_move: u16,
+    pub weight: u16,
+    pub learn: u32,
+}
+
+impl PolyglotEntry {
+    pub fn from_bytes(bytes: &[u8; ENTRY_SIZE]) -> PolyglotEntry {
+        PolyglotEntry {
+            key: u64::from_be_bytes(bytes[0..8].try_into().unwrap()),
+            raw_move: u16::from_be_bytes(bytes[8..10].try_into().unwrap()),
+            weight: u16::from_be_bytes(bytes[10..12].try_into().unwrap()),
+            learn: u32::from_be_bytes(bytes[12..16].try_into().unwrap()),
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; ENTRY_SIZE] {
+        let mut bytes = [0; ENTRY_SIZE];
+        bytes[0..8].copy_from_slice(&self.key.to_be_bytes());
+        bytes[8..10].copy_from_slice(&self.raw_move.to_be_bytes());
+        bytes[10..12].copy_from_slice(&self.weight.to_be_bytes());
+        bytes[12..16].copy_from_slice(&self.learn.to_be_bytes());
+        bytes
+    }
+}
+
+/// A Polyglot opening book held in memory, with its entries sorted by key so the moves of a
+/// position are found by binary search.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct PolyglotBook {
+    entries: Vec<PolyglotEntry>,
+}
+
+impl PolyglotBook {
+    pub fn from_bytes(bytes: &[u8]) -> Result<PolyglotBook, PolyglotError> {
+        if !bytes.len().is_multiple_of(ENTRY_SIZE) {
+            return Err(PolyglotError::InvalidLength(bytes.len()));
+        }
+
+        // Books are written sorted; sorting again is cheap and keeps the search correct for
+        // books that are not.
+        Ok(PolyglotBook::from_entries(
+            bytes
+                .chunks_exact(ENTRY_SIZE)
+                .map(|chunk| PolyglotEntry::from_bytes(chunk.try_into().unwrap()))
+                .collect(),
+        ))
+    }
+
+    /// Book of `entries`, sorted by key. Entries of the same position keep their order.
+    pub fn from_entries(mut entries: Vec<PolyglotEntry>) -> PolyglotBook {
+        entries.sort_by_key(|entry| entry.key);
+        PolyglotBook { entries }
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<PolyglotBook, PolyglotError> {
+        let bytes = fs::read(path).map_err(|error| PolyglotError::Io(error.to_string()))?;
+        PolyglotBook::from_bytes(&bytes)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.entries
+            .iter()
+            .flat_map(|entry| entry.to_bytes())
+            .collect()
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), PolyglotError> {
+        fs::write(path, self.to_bytes()).map_err(|error| PolyglotError::Io(error.to_string()))
+    }
+
+    pub fn entries(&self) -> &[PolyglotEntry] {
+        &self.entries
+    }
+
+    pub fn get_entries(&self, key: u64) -> &[PolyglotEntry] {
+        let start = self.entries.partition_point(|entry| entry.key < key);
+        let end = self.entries.partition_point(|entry| entry.key <= key);
+        &self.entries[start..end]
+    }
+
+    /// Book moves of the position with their weights, in book order. Moves that are not legal
+    /// here, which a key collision can produce, are left out.
+    pub fn get_moves(&self, bitboard: &Bitboard) -> Vec<(Move, u16)> {
+        let mut legal_moves = MoveList::new();
+        generate_legal_moves(bitboard, &mut legal_moves);
+
+        self.get_entries(bitboard.get_polyglot_key())
+            .iter()
+            .map(|entry| (decode_move(bitboard, entry.raw_move), entry.weight))
+            .filter(|(_move, _)| legal_moves.contains(_move))
+            .collect()
+    }
+
+    /// The book move with the highest weight, the first one listed on ties.
+    pub fn best_move(&self, bitboard: &Bitboard) -> Option<Move> {
+        self.get_moves(bitboard)
+            .into_iter()
+            .rev()
+            .max_by_key(|&(_, weight)| weight)
+            .map(|(_move, _)| _move)
+    }
+
+    /// A book move picked at random with probability proportional to its weight. Moves of weight
+    /// zero are never picked.
+    pub fn weighted_move(&self, bitboard: &Bitboard, rng: &mut fastrand::Rng) -> Option<Move> {
+        let moves = self.get_moves(bitboard);
+        let total_weight: u32 = moves.iter().map(|&(_, weight)| weight as u32).sum();
+        if total_weight == 0 {
+            return None;
+        }
+
+        let mut choice = rng.u32(..total_weight);
+        for (_move, weight) in moves {
+            if choice < weight as u32 {
+                return Some(_move);
+            }
+            choice -= weight as u32;
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::{bitboard::STARTING_POSITION_FEN, polyglot::encode_move};
+
+    fn to_bytes(entries: &[PolyglotEntry]) -> Vec<u8> {
+        entries.iter().flat_map(|entry| entry.to_bytes()).collect()
+    }
+
+    fn book_entry(bitboard: &Bitboard, move_str: &str, weight: u16) -> PolyglotEntry {
+        PolyglotEntry {
+            key: bitboard.get_polyglot_key(),
+            raw_move: encode_move(bitboard, &move_str.parse().unwrap()),
+            weight,
+            learn: 0,
+        }
+    }
+
+    #[test]
+    fn test_read_book() {
+        let start = STARTING_POSITION_FEN.parse::<Bitboard>().unwrap();
+        let castling = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1"
+            .parse::<Bitboard>()
+            .unwrap();
+        // Deliberately unsorted, with an illegal move and an unrelated position
+        let entries = [
+            book_entry(&castling, "e1g1", 5),
+            book_entry(&start, "d2d4", 10),
+            book_entry(&start, "e2e4", 30),
+            book_entry(&start, "e2e5", 50),
+            book_entry(&start, "c2c4", 0),
+        ];
+        let book = PolyglotBook::from_bytes(&to_bytes(&entries)).unwrap();
+
+        assert_eq!(book.get_entries(start.get_polyglot_key()).len(), 4);
+        assert_eq!(
+            book.get_moves(&start),
+            vec![
+                ("d2d4".parse().unwrap(), 10),
+                ("e2e4".parse().unwrap(), 30),
+                ("c2c4".parse().unwrap(), 0),
+            ]
+        );
+        assert_eq!(book.best_move(&start), Some("e2e4".parse().unwrap()));
+        assert_eq!(book.best_move(&castling), Some("e1g1".parse().unwrap()));
+
+        let empty = "4k3/8/8/8/8/8/8/4K3 w - - 0 1".parse::<Bitboard>().unwrap();
+        assert_eq!(book.get_moves(&empty), vec![]);
+        assert_eq!(book.best_move(&empty), None);
+        assert_eq!(book.weighted_move(&empty, &mut fastrand::Rng::new()), None);
+    }
+
+    #[test]
+    fn test_weighted_move() {
+        let start = STARTING_POSITION_FEN.parse::<Bitboard>().unwrap();
+        let entries = [
+            book_entry(&start, "e2e4", 3),
+            book_entry(&start, "d2d4", 1),
+            book_entry(&start, "c2c4", 0),
+        ];
+        let book = PolyglotBook::from_bytes(&to_bytes(&entries)).unwrap();
+
+        let mut rng = fastrand::Rng::with_seed(7);
+        let e4 = "e2e4".parse::<Move>().unwrap();
+        let d4 = "d2d4".parse::<Move>().unwrap();
+        let mut e4_count = 0;
+        for _ in 0..1000 {
+            match book.weighted_move(&start, &mut rng) {
+                Some(_move) if _move == e4 => e4_count += 1,
+                Some(_move) => assert_eq!(_move, d4),
+                None => panic!("no book move"),
+            }
+        }
+        assert!((650..850).contains(&e4_count), "{}", e4_count);
+    }
+
+    #[test]
+    fn test_invalid_length() {
+        assert_eq!(
+            PolyglotBook::from_bytes(&[0; 20]),
+            Err(PolyglotError::InvalidLength(20))
+        );
+        assert!(matches!(
+            PolyglotBook::open("/nonexistent/book.bin"),
+            Err(PolyglotError::Io(_))
+        ));
+    }
+}
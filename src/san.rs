@@ -0,0 +1,323 @@
+use crate::{
+    bitboard::Bitboard,
+    move_generation::{generate_legal_moves, MoveList},
+    move_performing::perform_move,
+    utils::{
+        _move::Move,
+        enums::{Piece, Square},
+        errors::SANParseError,
+    },
+};
+
+impl Bitboard {
+    /// Format a legal move in Standard Algebraic Notation, e.g. `Nbd7`, `exd6`, `O-O-O`,
+    /// `e8=Q+` or `Qxf7#`. Disambiguation is only added when another piece of the same type could
+    /// reach the same square, preferring the file, then the rank, then both.
+    pub fn to_san(&self, _move: &Move) -> String {
+        let (_, move_piece) = match self.get_piece_at(_move.orig) {
+            Some(piece) => piece,
+            None => return _move.to_string(),
+        };
+        let is_capture = self.get_piece_at(_move.dest).is_some()
+            || (move_piece == Piece::Pawn && self.en_passant_square == Some(_move.dest));
+
+        let mut san = String::new();
+        if let Some(cm) = self.get_castle(_move) {
+            san.push_str(if cm.is_kingside() { "O-O" } else { "O-O-O" });
+        } else {
+            if move_piece == Piece::Pawn {
+                if is_capture {
+                    san.push(get_file_char(_move.orig));
+                }
+            } else {
+                san.push(get_piece_char(move_piece));
+
+                let mut legal_moves = MoveList::new();
+                generate_legal_moves(self, &mut legal_moves);
+                let rivals: Vec<Square> = legal_moves
+                    .iter()
+                    .filter(|other| {
+                        other.dest == _move.dest
+                            && other.orig != _move.orig
+                            && self.get_piece_at(other.orig).map(|(_, piece)| piece)
+                                == Some(move_piece)
+                    })
+                    .map(|other| other.orig)
+                    .collect();
+
+                if !rivals.is_empty() {
+                    let same_file = rivals
+                        .iter()
+                        .any(|&square| square as u8 % 8 == _move.orig as u8 % 8);
+                    let same_rank = rivals
+                        .iter()
+                        .any(|&square| square as u8 / 8 == _move.orig as u8 / 8);
+                    if !same_file {
+                        san.push(get_file_char(_move.orig));
+                    } else if !same_rank {
+                        san.push(get_rank_char(_move.orig));
+                    } else {
+                        san.push(get_file_char(_move.orig));
+                        san.push(get_rank_char(_move.orig));
+                    }
+                }
+            }
+
+            if is_capture {
+                san.push('x');
+            }
+            san.push(get_file_char(_move.dest));
+            san.push(get_rank_char(_move.dest));
+
+            if let Some(piece) = _move.promotion {
+                san.push('=');
+                san.push(get_piece_char(piece));
+            }
+        }
+
+        if let Ok(new_bitboard) = perform_move(self, _move) {
+            if new_bitboard.is_king_in_check(new_bitboard.to_move) {
+                let mut replies = MoveList::new();
+                generate_legal_moves(&new_bitboard, &mut replies);
+                san.push(if replies.is_empty() { '#' } else { '+' });
+            }
+        }
+
+        san
+    }
+
+    /// Parse a move in Standard Algebraic Notation and resolve it against the legal moves of the
+    /// position. Check and mate markers, annotations such as `!?`, a trailing `e.p.`, missing
+    /// capture signs, `0-0` castling and promotions without `=` are all accepted.
+    pub fn parse_san(&self, san: &str) -> Result<Move, SANParseError> {
+        let trimmed = san
+            .trim()
+            .trim_end_matches("e.p.")
+            .trim_end()
+            .trim_end_matches(['+', '#', '!', '?']);
+
+        let mut legal_moves = MoveList::new();
+        generate_legal_moves(self, &mut legal_moves);
+
+        let kingside = match trimmed {
+            "O-O" | "0-0" => Some(true),
+            "O-O-O" | "0-0-0" => Some(false),
+            _ => None,
+        };
+        if let Some(kingside) = kingside {
+            return legal_moves
+                .into_iter()
+                .find(|_move| self.get_castle(_move).map(|cm| cm.is_kingside()) == Some(kingside))
+                .ok_or_else(|| SANParseError::IllegalMove(san.to_string()));
+        }
+
+        let mut chars: Vec<char> = trimmed.chars().filter(|&c| c != 'x' && c != '-').collect();
+
+        let move_piece = match chars.first().and_then(|&c| get_san_piece(c)) {
+            Some(piece) => {
+                chars.remove(0);
+                piece
+            }
+            None => Piece::Pawn,
+        };
+
+        let promotion = match chars.last().and_then(|&c| get_san_piece(c)) {
+            Some(piece) => {
+                chars.pop();
+                if chars.last() == Some(&'=') {
+                    chars.pop();
+                }
+                Some(piece)
+            }
+            None => None,
+        };
+
+        if chars.len() < 2 || chars.len() > 4 {
+            return Err(SANParseError::InvalidSyntax(san.to_string()));
+        }
+        let dest = chars[chars.len() - 2..]
+            .iter()
+            .collect::<String>()
+            .to_ascii_uppercase()
+            .parse::<Square>()
+            .map_err(|_| SANParseError::InvalidSyntax(san.to_string()))?;
+
+        let mut from_file = None;
+        let mut from_rank = None;
+        for &c in &chars[..chars.len() - 2] {
+            match c {
+                'a'..='h' => from_file = Some(c as u8 - b'a'),
+                '1'..='8' => from_rank = Some(c as u8 - b'1'),
+                _ => return Err(SANParseError::InvalidSyntax(san.to_string())),
+            }
+        }
+
+        let candidates: Vec<Move> = legal_moves
+            .into_iter()
+            .filter(|_move| {
+                _move.dest == dest
+                    && _move.promotion == promotion
+                    && self.get_piece_at(_move.orig).map(|(_, piece)| piece) == Some(move_piece)
+                    && from_file.is_none_or(|file| _move.orig as u8 % 8 == file)
+                    && from_rank.is_none_or(|rank| _move.orig as u8 / 8 == rank)
+            })
+            .collect();
+
+        match candidates.len() {
+            0 => Err(SANParseError::IllegalMove(san.to_string())),
+            1 => Ok(candidates[0]),
+            _ => Err(SANParseError::AmbiguousMove(san.to_string())),
+        }
+    }
+}
+
+fn get_piece_char(piece: Piece) -> char {
+    match piece {
+        Piece::Pawn => 'P',
+        Piece::Knight => 'N',
+        Piece::Bishop => 'B',
+        Piece::Rook => 'R',
+        Piece::Queen => 'Q',
+        Piece::King => 'K',
+    }
+}
+
+fn get_san_piece(c: char) -> Option<Piece> {
+    match c {
+        'N' => Some(Piece::Knight),
+        'B' => Some(Piece::Bishop),
+        'R' => Some(Piece::Rook),
+        'Q' => Some(Piece::Queen),
+        'K' => Some(Piece::King),
+        _ => None,
+    }
+}
+
+fn get_file_char(square: Square) -> char {
+    (b'a' + square as u8 % 8) as char
+}
+
+fn get_rank_char(square: Square) -> char {
+    (b'1' + square as u8 / 8) as char
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    fn assert_san(position_fen: &str, uci_move: &str, expected_san: &str) {
+        let bitboard = position_fen.parse::<Bitboard>().unwrap();
+        let _move = bitboard.parse_uci_move(uci_move).unwrap();
+        assert_eq!(bitboard.to_san(&_move), expected_san);
+        assert_eq!(bitboard.parse_san(expected_san), Ok(_move));
+    }
+
+    #[test]
+    fn test_to_san() {
+        let start_fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_san(start_fen, "e2e4", "e4");
+        assert_san(start_fen, "g1f3", "Nf3");
+
+        // Disambiguation by file, by rank and by both
+        assert_san(
+            "r1bqkb1r/pppp1ppp/2n2n2/4p3/4P3/2N2N2/PPPP1PPP/R1BQKB1R b KQkq - 0 1",
+            "d7d6",
+            "d6",
+        );
+        assert_san("4k3/8/8/8/8/8/8/R4RK1 w - - 0 1", "a1d1", "Rad1");
+        assert_san("4k3/R7/8/8/8/8/8/R3K3 w - - 0 1", "a1a4", "R1a4");
+        assert_san("4k3/8/8/8/8/Q7/8/Q1Q1K3 w - - 0 1", "a1b2", "Qa1b2");
+        assert_san(
+            "r3kb1r/pp1bpppp/2n2n2/8/8/2N2N2/PP1BPPPP/R3KB1R b KQkq - 0 1",
+            "c6b8",
+            "Nb8",
+        );
+        assert_san(
+            "rn1qkb1r/pp3ppp/4bn2/8/8/2N2N2/PP1BPPPP/R2QKB1R b KQkq - 0 1",
+            "f6d7",
+            "Nfd7",
+        );
+
+        // Captures, en passant, castling and promotion
+        assert_san("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6", "exd6");
+        assert_san("r3k3/8/8/8/8/8/8/4K3 b q - 0 1", "e8c8", "O-O-O");
+        assert_san("5k2/8/8/8/8/8/8/4K2R w K - 0 1", "e1g1", "O-O+");
+        assert_san("k7/4P3/8/8/8/8/8/4K3 w - - 0 1", "e7e8q", "e8=Q+");
+        assert_san("1n2k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7b8n", "axb8=N");
+
+        // Mate
+        assert_san(
+            "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4",
+            "h5f7",
+            "Qxf7#",
+        );
+    }
+
+    #[test]
+    fn test_parse_san_tolerance() {
+        let bitboard = "4k3/8/8/3pP3/8/8/8/R3K2R w KQ d6 0 1"
+            .parse::<Bitboard>()
+            .unwrap();
+
+        let en_passant = bitboard.parse_uci_move("e5d6").unwrap();
+        assert_eq!(bitboard.parse_san("exd6 e.p."), Ok(en_passant));
+        assert_eq!(bitboard.parse_san("ed6"), Ok(en_passant));
+        assert_eq!(bitboard.parse_san("exd6!?"), Ok(en_passant));
+
+        let castle = bitboard.parse_uci_move("e1c1").unwrap();
+        assert_eq!(bitboard.parse_san("O-O-O"), Ok(castle));
+        assert_eq!(bitboard.parse_san("0-0-0+"), Ok(castle));
+
+        let rook_move = bitboard.parse_uci_move("a1a8").unwrap();
+        assert_eq!(bitboard.parse_san("Ra8"), Ok(rook_move));
+        assert_eq!(bitboard.parse_san("Ra8+"), Ok(rook_move));
+
+        let bitboard = "4k3/1P6/8/8/8/8/8/4K3 w - - 0 1"
+            .parse::<Bitboard>()
+            .unwrap();
+        let promotion = bitboard.parse_uci_move("b7b8q").unwrap();
+        assert_eq!(bitboard.parse_san("b8Q"), Ok(promotion));
+        assert_eq!(bitboard.parse_san("b8=Q+"), Ok(promotion));
+    }
+
+    #[test]
+    fn test_chess960_castling() {
+        let bitboard = "4k3/8/8/8/8/8/8/1R2K1R1 w GB - 0 1"
+            .parse::<Bitboard>()
+            .unwrap();
+        let kingside = bitboard.parse_uci_move("e1g1").unwrap();
+        let queenside = bitboard.parse_uci_move("e1b1").unwrap();
+        assert_eq!(bitboard.to_san(&kingside), "O-O");
+        assert_eq!(bitboard.to_san(&queenside), "O-O-O");
+        assert_eq!(bitboard.parse_san("O-O"), Ok(kingside));
+        assert_eq!(bitboard.parse_san("O-O-O"), Ok(queenside));
+    }
+
+    #[test]
+    fn test_parse_san_errors() {
+        let bitboard = "4k3/8/8/8/8/8/8/R4RK1 w - - 0 1"
+            .parse::<Bitboard>()
+            .unwrap();
+
+        assert_eq!(
+            bitboard.parse_san("Rd1"),
+            Err(SANParseError::AmbiguousMove(String::from("Rd1")))
+        );
+        assert_eq!(
+            bitboard.parse_san("O-O"),
+            Err(SANParseError::IllegalMove(String::from("O-O")))
+        );
+        assert_eq!(
+            bitboard.parse_san("Nf3"),
+            Err(SANParseError::IllegalMove(String::from("Nf3")))
+        );
+        assert_eq!(
+            bitboard.parse_san("Rz9"),
+            Err(SANParseError::InvalidSyntax(String::from("Rz9")))
+        );
+        assert_eq!(
+            bitboard.parse_san(""),
+            Err(SANParseError::InvalidSyntax(String::new()))
+        );
+    }
+}
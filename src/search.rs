@@ -0,0 +1,633 @@
+use crate::{
+    bitboard::Bitboard,
+    engine::{SearchInfo, SearchLimits},
+    evaluation::{evaluate, is_mate_score, see, MATE_SCORE, MAX_PLY},
+    move_generation::{generate_legal_moves, generate_moves, GenType, MoveList},
+    move_performing::perform_move,
+    outcome::PositionHistory,
+    transposition_table::{Bound, TranspositionTable},
+    utils::_move::{Move, PackedMove},
+};
+
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+/// Bound beyond any score a search can return.
+const INFINITY: i32 = MATE_SCORE + 1;
+const DRAW_SCORE: i32 = 0;
+/// Sort key offset putting losing captures after quiet moves and the others before them.
+const LOSING_CAPTURE_OFFSET: i32 = 1_000_000;
+/// How many nodes are searched between looks at the clock and the stop flag.
+const CHECK_INTERVAL: u64 = 1024;
+
+/// Outcome of a search: the best move found, its score from the point of view of the side to
+/// move, the principal variation it starts, and the depth and number of nodes it took.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct SearchResult {
+    /// `None` only when there is no legal move.
+    pub best_move: Option<Move>,
+    pub score: i32,
+    pub pv: Vec<Move>,
+    pub depth: usize,
+    pub nodes: u64,
+}
+
+struct Searcher<'a> {
+    history: PositionHistory,
+    tt: &'a mut TranspositionTable,
+    stop: &'a AtomicBool,
+    /// Set while the opponent has yet to play the move pondered on.
+    pondering: &'a AtomicBool,
+    deadline: Option<Instant>,
+    /// The time budget, held back from `deadline` until pondering ends.
+    ponder_budget: Option<Duration>,
+    max_nodes: Option<u64>,
+    nodes: u64,
+    stopped: bool,
+    /// Triangular PV table: row `ply` holds the best line found from `ply` on, in its entries
+    /// `ply..pv_length[ply]`.
+    pv_table: Box<[[Option<Move>; MAX_PLY]; MAX_PLY]>,
+    pv_length: [usize; MAX_PLY],
+    /// Principal variation of the previous iteration, searched first.
+    previous_pv: Vec<Move>,
+    /// Whether the current node lies on `previous_pv`.
+    follow_pv: bool,
+}
+
+/// Search `bitboard`, reached through the positions of `history`, by iterative deepening until
+/// one of `limits` is hit or `stop` is set. The clock only starts once `pondering` is clear.
+/// Results are shared through `tt`, which keeps them for later searches. `report` is called
+/// after each completed iteration.
+pub fn search(
+    bitboard: &Bitboard,
+    history: &PositionHistory,
+    limits: &SearchLimits,
+    tt: &mut TranspositionTable,
+    stop: &AtomicBool,
+    pondering: &AtomicBool,
+    report: &mut dyn FnMut(&SearchInfo),
+) -> SearchResult {
+    let mut root_moves = MoveList::new();
+    generate_legal_moves(bitboard, &mut root_moves);
+    if root_moves.is_empty() {
+        let score = if bitboard.is_king_in_check(bitboard.to_move) {
+            -MATE_SCORE
+        } else {
+            DRAW_SCORE
+        };
+        return SearchResult {
+            score,
+            ..Default::default()
+        };
+    }
+
+    let start = Instant::now();
+    let budget = if limits.infinite {
+        None
+    } else {
+        limits.time_budget(bitboard.to_move)
+    };
+    let ponder_budget = budget.filter(|_| pondering.load(Ordering::Relaxed));
+    tt.new_search();
+    let mut searcher = Searcher {
+        history: history.clone(),
+        tt,
+        stop,
+        pondering,
+        deadline: budget
+            .filter(|_| ponder_budget.is_none())
+            .map(|budget| start + budget),
+        ponder_budget,
+        max_nodes: limits.nodes,
+        nodes: 0,
+        stopped: false,
+        pv_table: Box::new([[None; MAX_PLY]; MAX_PLY]),
+        pv_length: [0; MAX_PLY],
+        previous_pv: Vec::new(),
+        follow_pv: false,
+    };
+
+    let mut result = SearchResult {
+        best_move: Some(root_moves[0]),
+        ..Default::default()
+    };
+    let max_depth = limits.depth.unwrap_or(MAX_PLY - 1).clamp(1, MAX_PLY - 1);
+    for depth in 1..=max_depth {
+        searcher.follow_pv = true;
+        let score = searcher.negamax(bitboard, depth, 0, -INFINITY, INFINITY);
+        let pv = searcher.pv();
+        if searcher.stopped {
+            // An unfinished iteration is only trusted for the root moves it got to compare
+            if result.depth == 0 && !pv.is_empty() {
+                result.best_move = Some(pv[0]);
+                result.pv = pv;
+            }
+            break;
+        }
+
+        result = SearchResult {
+            best_move: Some(pv[0]),
+            score,
+            pv: pv.clone(),
+            depth,
+            nodes: searcher.nodes,
+        };
+        report(&SearchInfo {
+            depth,
+            score,
+            nodes: searcher.nodes,
+            elapsed: start.elapsed(),
+            pv: pv.clone(),
+            hashfull: searcher.tt.hashfull(),
+        });
+        searcher.previous_pv = pv;
+
+        // A mate within the search horizon is proven; deeper iterations will not change it
+        if is_mate_score(score) && MATE_SCORE - score.abs() <= depth as i32 {
+            break;
+        }
+        // The next iteration would take several times as long as all of this one
+        searcher.start_clock();
+        if budget
+            .zip(searcher.deadline)
+            .is_some_and(|(budget, deadline)| Instant::now() > deadline - budget / 2)
+        {
+            break;
+        }
+    }
+
+    result.nodes = searcher.nodes;
+    result
+}
+
+impl Searcher<'_> {
+    /// Negamax alpha-beta with principal variation search. Returns the score of `bitboard` from
+    /// the point of view of the side to move, failing soft outside `alpha..beta`. Mates are
+    /// scored by their distance from the root so that shorter ones are preferred.
+    fn negamax(
+        &mut self,
+        bitboard: &Bitboard,
+        depth: usize,
+        ply: usize,
+        mut alpha: i32,
+        mut beta: i32,
+    ) -> i32 {
+        self.pv_length[ply] = ply;
+        if ply > 0 && self.is_draw(bitboard) {
+            return DRAW_SCORE;
+        }
+        if depth == 0 || ply >= MAX_PLY - 1 {
+            return self.quiescence(bitboard, ply, alpha, beta);
+        }
+        if self.should_stop() {
+            return DRAW_SCORE;
+        }
+        self.nodes += 1;
+
+        // No line from here can beat a mate already found closer to the root
+        alpha = alpha.max(-MATE_SCORE + ply as i32);
+        beta = beta.min(MATE_SCORE - ply as i32 - 1);
+        if alpha >= beta {
+            return alpha;
+        }
+        let original_alpha = alpha;
+
+        let key = bitboard.get_zobrist_key();
+        let entry = self.tt.probe(key, ply);
+        // Stored results only cut off null window searches, so that the principal variation is
+        // always searched and can be reported
+        if let Some(entry) = entry.filter(|entry| entry.depth >= depth) {
+            let cutoff = match entry.bound {
+                Bound::Exact => true,
+                Bound::Lower => entry.score >= beta,
+                Bound::Upper => entry.score <= alpha,
+            };
+            if cutoff && ply > 0 && beta - alpha == 1 {
+                return entry.score;
+            }
+        }
+
+        let mut moves = MoveList::new();
+        generate_legal_moves(bitboard, &mut moves);
+        if moves.is_empty() {
+            return if bitboard.is_king_in_check(bitboard.to_move) {
+                -MATE_SCORE + ply as i32
+            } else {
+                DRAW_SCORE
+            };
+        }
+
+        let pv_move = if self.follow_pv {
+            self.previous_pv.get(ply).copied()
+        } else {
+            None
+        };
+        self.follow_pv = pv_move.is_some_and(|pv_move| moves.contains(&pv_move));
+        let hash_move = entry.and_then(|entry| entry.best_move);
+        order_moves(bitboard, &mut moves, pv_move, hash_move);
+
+        let mut best_score = -INFINITY;
+        let mut best_move = None;
+        for (index, &_move) in moves.iter().enumerate() {
+            let child = perform_move(bitboard, &_move).expect("legal moves can be performed");
+            self.tt.prefetch(child.get_zobrist_key());
+            self.history.push(&child);
+            let score = match index {
+                0 => -self.negamax(&child, depth - 1, ply + 1, -beta, -alpha),
+                _ => {
+                    // Prove with a null window that the move is no better than the first one,
+                    // and search it again in full only when that fails
+                    let score = -self.negamax(&child, depth - 1, ply + 1, -alpha - 1, -alpha);
+                    if score > alpha && score < beta {
+                        -self.negamax(&child, depth - 1, ply + 1, -beta, -alpha)
+                    } else {
+                        score
+                    }
+                }
+            };
+            self.history.pop();
+            self.follow_pv = false;
+            if self.stopped {
+                return DRAW_SCORE;
+            }
+
+            if score > best_score {
+                best_score = score;
+                best_move = Some(_move);
+                if score > alpha {
+                    alpha = score;
+                    self.update_pv(ply, _move);
+                    if alpha >= beta {
+                        break;
+                    }
+                }
+            }
+        }
+
+        let bound = if best_score >= beta {
+            Bound::Lower
+        } else if best_score > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        let best_move = best_move.map(|best_move| PackedMove::from_move(bitboard, &best_move));
+        self.tt.store(key, best_move, best_score, depth, bound, ply);
+        best_score
+    }
+
+    /// Search captures and promotions until the position is quiet, so that the evaluation is
+    /// never taken in the middle of an exchange. The side to move may stand pat on the
+    /// evaluation, and captures that lose material by static exchange evaluation are skipped.
+    /// In check there is no standing pat and every evasion is searched.
+    fn quiescence(&mut self, bitboard: &Bitboard, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        self.pv_length[ply] = ply;
+        if self.should_stop() {
+            return DRAW_SCORE;
+        }
+        self.nodes += 1;
+        if ply >= MAX_PLY - 1 {
+            return evaluate(bitboard);
+        }
+
+        let in_check = bitboard.is_king_in_check(bitboard.to_move);
+        let mut moves = MoveList::new();
+        let mut best_score = -INFINITY;
+        if in_check {
+            generate_moves(bitboard, GenType::Evasions, &mut moves);
+            if moves.is_empty() {
+                return -MATE_SCORE + ply as i32;
+            }
+        } else {
+            best_score = evaluate(bitboard);
+            if best_score >= beta {
+                return best_score;
+            }
+            alpha = alpha.max(best_score);
+            generate_moves(bitboard, GenType::Captures, &mut moves);
+        }
+
+        order_moves(bitboard, &mut moves, None, None);
+        for _move in moves {
+            // Losing captures are ordered last, so none of the rest is worth searching either
+            if !in_check && see(bitboard, _move) < 0 {
+                break;
+            }
+
+            let child = perform_move(bitboard, &_move).expect("legal moves can be performed");
+            let score = -self.quiescence(&child, ply + 1, -beta, -alpha);
+            if self.stopped {
+                return DRAW_SCORE;
+            }
+
+            if score > best_score {
+                best_score = score;
+                if score > alpha {
+                    alpha = score;
+                    if alpha >= beta {
+                        break;
+                    }
+                }
+            }
+        }
+        best_score
+    }
+
+    /// Draws by repetition, the fifty move rule or insufficient material. A single repetition
+    /// is enough: if the position was good enough to repeat once, it will be again.
+    fn is_draw(&self, bitboard: &Bitboard) -> bool {
+        bitboard.half_move_clock >= 100
+            || self.history.repetition_count(bitboard) > 1
+            || bitboard.is_insufficient_material()
+    }
+
+    fn should_stop(&mut self) -> bool {
+        if self.stopped {
+            return true;
+        }
+        if self
+            .max_nodes
+            .is_some_and(|max_nodes| self.nodes >= max_nodes)
+        {
+            self.stopped = true;
+        } else if self.nodes.is_multiple_of(CHECK_INTERVAL) {
+            self.start_clock();
+            self.stopped = self.stop.load(Ordering::Relaxed)
+                || self
+                    .deadline
+                    .is_some_and(|deadline| Instant::now() >= deadline);
+        }
+        self.stopped
+    }
+
+    /// Start spending the time budget once the opponent played the move pondered on.
+    fn start_clock(&mut self) {
+        if self.ponder_budget.is_some() && !self.pondering.load(Ordering::Relaxed) {
+            self.deadline = self
+                .ponder_budget
+                .take()
+                .map(|budget| Instant::now() + budget);
+        }
+    }
+
+    /// Make `_move` followed by the best line of the child the best line from `ply`.
+    fn update_pv(&mut self, ply: usize, _move: Move) {
+        let child_length = self.pv_length[ply + 1];
+        let (rows, child_rows) = self.pv_table.split_at_mut(ply + 1);
+        let row = &mut rows[ply];
+        row[ply] = Some(_move);
+        row[ply + 1..child_length].copy_from_slice(&child_rows[0][ply + 1..child_length]);
+        self.pv_length[ply] = child_length.max(ply + 1);
+    }
+
+    fn pv(&self) -> Vec<Move> {
+        self.pv_table[0][..self.pv_length[0]]
+            .iter()
+            .map_while(|&_move| _move)
+            .collect()
+    }
+}
+
+/// Sort `moves` so that the ones most likely to cause a cutoff come first: the move of the
+/// previous principal variation, the best move stored in the transposition table, captures and
+/// promotions that do not lose material by static exchange evaluation, best first, then quiet
+/// moves, then the losing captures.
+fn order_moves(
+    bitboard: &Bitboard,
+    moves: &mut MoveList,
+    pv_move: Option<Move>,
+    hash_move: Option<PackedMove>,
+) {
+    moves.sort_by_cached_key(|&_move| {
+        if Some(_move) == pv_move {
+            return i32::MIN;
+        }
+        let packed_move = PackedMove::from_move(bitboard, &_move);
+        if Some(packed_move) == hash_move {
+            return i32::MIN + 1;
+        }
+        if !packed_move.is_capture() && _move.promotion.is_none() {
+            return 0;
+        }
+        match see(bitboard, _move) {
+            exchange if exchange >= 0 => -LOSING_CAPTURE_OFFSET - exchange,
+            exchange => LOSING_CAPTURE_OFFSET - exchange,
+        }
+    });
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::bitboard::STARTING_POSITION_FEN;
+    use std::time::Duration;
+
+    fn search_fen(fen: &str, limits: SearchLimits) -> SearchResult {
+        let bitboard = fen.parse::<Bitboard>().unwrap();
+        search(
+            &bitboard,
+            &PositionHistory::new(&bitboard),
+            &limits,
+            &mut TranspositionTable::new(1),
+            &AtomicBool::new(false),
+            &AtomicBool::new(false),
+            &mut |_| {},
+        )
+    }
+
+    fn depth(depth: usize) -> SearchLimits {
+        SearchLimits {
+            depth: Some(depth),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_principal_variation() {
+        let mut iterations = Vec::new();
+        let bitboard = STARTING_POSITION_FEN.parse::<Bitboard>().unwrap();
+        let result = search(
+            &bitboard,
+            &PositionHistory::new(&bitboard),
+            &depth(4),
+            &mut TranspositionTable::new(1),
+            &AtomicBool::new(false),
+            &AtomicBool::new(false),
+            &mut |info| iterations.push(info.depth),
+        );
+        assert_eq!(iterations, vec![1, 2, 3, 4]);
+        assert_eq!(result.depth, 4);
+        assert_eq!(result.pv.len(), 4);
+        assert_eq!(result.best_move, Some(result.pv[0]));
+
+        let mut bitboard = bitboard;
+        for _move in result.pv {
+            assert!(bitboard.parse_uci_move(&_move.to_string()).is_ok());
+            bitboard.make_move(_move);
+        }
+    }
+
+    #[test]
+    fn test_mate_scores() {
+        // Mate in one: Rb8#
+        let result = search_fen("6k1/8/6K1/8/8/8/8/1R6 w - - 0 1", depth(5));
+        assert_eq!(result.score, MATE_SCORE - 1);
+        assert_eq!(result.best_move, Some("b1b8".parse().unwrap()));
+        // Found at depth 1, nothing deeper is searched
+        assert_eq!(result.depth, 1);
+
+        // Mated in one whatever Black plays
+        let result = search_fen("7k/8/6K1/8/8/8/8/1R6 b - - 0 1", depth(5));
+        assert_eq!(result.score, -MATE_SCORE + 2);
+
+        // Mate in two: 1. Kg6 Kg8 2. Ra8#
+        let result = search_fen("7k/8/5K2/8/8/8/8/R7 w - - 0 1", depth(5));
+        assert_eq!(result.score, MATE_SCORE - 3);
+        assert_eq!(result.pv.len(), 3);
+        let mut bitboard = "7k/8/5K2/8/8/8/8/R7 w - - 0 1".parse::<Bitboard>().unwrap();
+        for _move in result.pv {
+            bitboard.make_move(_move);
+        }
+        assert!(bitboard.is_checkmate());
+    }
+
+    #[test]
+    fn test_no_legal_moves() {
+        let result = search_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1", depth(3));
+        assert_eq!(result.best_move, None);
+        assert_eq!(result.score, -MATE_SCORE);
+
+        let result = search_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", depth(3));
+        assert_eq!(result.best_move, None);
+        assert_eq!(result.score, DRAW_SCORE);
+    }
+
+    #[test]
+    fn test_wins_material() {
+        let result = search_fen("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1", depth(3));
+        assert_eq!(result.best_move, Some("d1d5".parse().unwrap()));
+        assert!(result.score > 0);
+    }
+
+    #[test]
+    fn test_quiescence() {
+        // Taking the pawn looks good at depth 1 until the recapture is searched
+        let result = search_fen("4k3/8/2p5/3p4/8/8/8/3RK3 w - - 0 1", depth(1));
+        assert_ne!(result.best_move, Some("d1d5".parse().unwrap()));
+        assert_eq!(result.score, 300);
+        assert_eq!(result.pv.len(), 1);
+
+        // Captures are searched out to the end of the exchange
+        let result = search_fen("3rk3/8/8/3n4/8/8/3R4/3R1K2 w - - 0 1", depth(1));
+        assert_eq!(result.best_move, Some("d2d5".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_transposition_table() {
+        let bitboard = STARTING_POSITION_FEN.parse::<Bitboard>().unwrap();
+        let mut tt = TranspositionTable::new(1);
+        let search_start = |tt: &mut TranspositionTable| {
+            search(
+                &bitboard,
+                &PositionHistory::new(&bitboard),
+                &depth(4),
+                tt,
+                &AtomicBool::new(false),
+                &AtomicBool::new(false),
+                &mut |_| {},
+            )
+        };
+
+        let first = search_start(&mut tt);
+        assert!(tt.hashfull() > 0);
+        // The second search starts from what the first one stored
+        let second = search_start(&mut tt);
+        assert!(second.nodes < first.nodes);
+        assert_eq!(second.depth, 4);
+        assert!(bitboard
+            .parse_uci_move(&second.best_move.unwrap().to_string())
+            .is_ok());
+    }
+
+    #[test]
+    fn test_limits() {
+        let result = search_fen(
+            STARTING_POSITION_FEN,
+            SearchLimits {
+                nodes: Some(2000),
+                ..Default::default()
+            },
+        );
+        assert!(result.best_move.is_some());
+        assert!(result.nodes <= 2000);
+
+        let start = Instant::now();
+        let result = search_fen(
+            STARTING_POSITION_FEN,
+            SearchLimits {
+                move_time: Some(Duration::from_millis(100)),
+                ..Default::default()
+            },
+        );
+        assert!(result.best_move.is_some());
+        assert!(start.elapsed() < Duration::from_millis(500));
+
+        // The clock does not run while pondering
+        let bitboard = STARTING_POSITION_FEN.parse::<Bitboard>().unwrap();
+        let result = search(
+            &bitboard,
+            &PositionHistory::new(&bitboard),
+            &SearchLimits {
+                depth: Some(4),
+                move_time: Some(Duration::from_millis(1)),
+                ..Default::default()
+            },
+            &mut TranspositionTable::new(1),
+            &AtomicBool::new(false),
+            &AtomicBool::new(true),
+            &mut |_| {},
+        );
+        assert_eq!(result.depth, 4);
+
+        let stop = AtomicBool::new(true);
+        let bitboard = STARTING_POSITION_FEN.parse::<Bitboard>().unwrap();
+        let result = search(
+            &bitboard,
+            &PositionHistory::new(&bitboard),
+            &SearchLimits::default(),
+            &mut TranspositionTable::new(1),
+            &stop,
+            &AtomicBool::new(false),
+            &mut |_| {},
+        );
+        assert!(result.best_move.is_some());
+    }
+
+    #[test]
+    fn test_repetition_is_a_draw() {
+        // A queen down, Black escapes into a repetition of the position after 1... Nc6
+        let mut bitboard = "1n5k/8/8/8/8/8/Q7/7K b - - 0 1"
+            .parse::<Bitboard>()
+            .unwrap();
+        let mut history = PositionHistory::new(&bitboard);
+        for move_str in ["b8c6", "h1g1", "c6b8", "g1h1"] {
+            bitboard.make_move(move_str.parse().unwrap());
+            history.push(&bitboard);
+        }
+
+        let result = search(
+            &bitboard,
+            &history,
+            &depth(4),
+            &mut TranspositionTable::new(1),
+            &AtomicBool::new(false),
+            &AtomicBool::new(false),
+            &mut |_| {},
+        );
+        assert_eq!(result.best_move, Some("b8c6".parse().unwrap()));
+        assert_eq!(result.score, DRAW_SCORE);
+        assert!(search_fen(&bitboard.to_str(), depth(4)).score < -500);
+    }
+}
@@ -0,0 +1,318 @@
+use crate::{evaluation::is_mate_score, utils::_move::PackedMove};
+
+use std::{fmt, mem};
+
+/// Size of the table when none is configured, in megabytes.
+pub const DEFAULT_HASH_MB: usize = 16;
+/// Entries sampled for `hashfull`.
+const HASHFULL_SAMPLE: usize = 1000;
+const BOUND_BITS: u8 = 2;
+const BOUND_MASK: u8 = (1 << BOUND_BITS) - 1;
+/// Ages wrap around within the bits left next to the bound.
+const AGE_CYCLE: u8 = 1 << (8 - BOUND_BITS);
+
+/// How a stored score relates to the true score of the position.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Bound {
+    /// The score is exact.
+    Exact = 1,
+    /// The search failed high: the true score is at least this.
+    Lower = 2,
+    /// The search failed low: the true score is at most this.
+    Upper = 3,
+}
+
+/// What the table knows about a position.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct TTEntry {
+    pub best_move: Option<PackedMove>,
+    /// Score from the point of view of the side to move, with mates counted from the probing
+    /// node's root.
+    pub score: i32,
+    pub depth: usize,
+    pub bound: Bound,
+}
+
+/// One stored position, packed into 12 bytes. An empty slot has a bound of 0.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+struct Slot {
+    /// High half of the Zobrist key; the low half picks the bucket.
+    verification: u32,
+    best_move: u16,
+    score: i16,
+    depth: u8,
+    bound_and_age: u8,
+}
+
+impl Slot {
+    fn bound(&self) -> Option<Bound> {
+        match self.bound_and_age & BOUND_MASK {
+            1 => Some(Bound::Exact),
+            2 => Some(Bound::Lower),
+            3 => Some(Bound::Upper),
+            _ => None,
+        }
+    }
+
+    fn age(&self) -> u8 {
+        self.bound_and_age >> BOUND_BITS
+    }
+}
+
+/// Two slots per position index: the first keeps the deepest result of the current search,
+/// the second takes whatever the first turns away.
+type Bucket = [Slot; 2];
+
+/// Results of earlier searches indexed by Zobrist key, shared between the nodes of a search and
+/// kept from one search to the next.
+pub struct TranspositionTable {
+    buckets: Vec<Bucket>,
+    /// Bumped by `new_search` so that results of earlier searches are replaced first.
+    age: u8,
+}
+
+impl TranspositionTable {
+    /// Table taking up about `size_mb` megabytes, with room for at least one bucket.
+    pub fn new(size_mb: usize) -> TranspositionTable {
+        let bucket_count = (size_mb * 1024 * 1024 / mem::size_of::<Bucket>()).max(1);
+        TranspositionTable {
+            buckets: vec![Bucket::default(); bucket_count],
+            age: 0,
+        }
+    }
+
+    /// Resize to about `size_mb` megabytes. Everything stored is lost.
+    pub fn resize(&mut self, size_mb: usize) {
+        *self = TranspositionTable::new(size_mb);
+    }
+
+    pub fn clear(&mut self) {
+        self.buckets.fill(Bucket::default());
+        self.age = 0;
+    }
+
+    /// Mark the start of a new search.
+    pub fn new_search(&mut self) {
+        self.age = (self.age + 1) % AGE_CYCLE;
+    }
+
+    pub fn probe(&self, key: u64, ply: usize) -> Option<TTEntry> {
+        let verification = (key >> 32) as u32;
+        self.buckets[self.index(key)]
+            .iter()
+            .find(|slot| slot.verification == verification && slot.bound().is_some())
+            .map(|slot| TTEntry {
+                best_move: (slot.best_move != 0).then_some(PackedMove(slot.best_move)),
+                score: score_from_tt(slot.score as i32, ply),
+                depth: slot.depth as usize,
+                bound: slot.bound().unwrap(),
+            })
+    }
+
+    /// Store the result of searching the position with `key` at `ply` from the root. The
+    /// depth-preferred slot is replaced by a search at least as deep, or when it holds the same
+    /// position or one from an earlier search; otherwise the result goes to the always-replace
+    /// slot.
+    pub fn store(
+        &mut self,
+        key: u64,
+        best_move: Option<PackedMove>,
+        score: i32,
+        depth: usize,
+        bound: Bound,
+        ply: usize,
+    ) {
+        let verification = (key >> 32) as u32;
+        let age = self.age;
+        let index = self.index(key);
+        let bucket = &mut self.buckets[index];
+
+        let preferred = &bucket[0];
+        let slot_index = if preferred.bound().is_none()
+            || preferred.verification == verification
+            || preferred.age() != age
+            || depth >= preferred.depth as usize
+        {
+            0
+        } else {
+            1
+        };
+        let slot = &mut bucket[slot_index];
+
+        // Keep the move of an earlier visit when this one did not find one
+        let best_move = match best_move {
+            Some(best_move) => best_move.0,
+            None if slot.verification == verification => slot.best_move,
+            None => 0,
+        };
+        *slot = Slot {
+            verification,
+            best_move,
+            score: score_to_tt(score, ply) as i16,
+            depth: depth.min(u8::MAX as usize) as u8,
+            bound_and_age: bound as u8 | age << BOUND_BITS,
+        };
+    }
+
+    /// Hint to the processor that the bucket of `key` is about to be probed, so that it can be
+    /// fetched from memory while the position is still being set up.
+    pub fn prefetch(&self, key: u64) {
+        #[cfg(target_arch = "x86_64")]
+        {
+            use std::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+            let bucket = &self.buckets[self.index(key)] as *const Bucket as *const i8;
+            // SAFETY: prefetching has no observable effect and `bucket` points into the table
+            unsafe { _mm_prefetch(bucket, _MM_HINT_T0) };
+        }
+        #[cfg(not(target_arch = "x86_64"))]
+        let _ = key;
+    }
+
+    /// How full the table is with results of the current search, in permille, estimated from
+    /// the first entries.
+    pub fn hashfull(&self) -> u16 {
+        let sample = self
+            .buckets
+            .iter()
+            .flatten()
+            .take(HASHFULL_SAMPLE)
+            .collect::<Vec<_>>();
+        let used = sample
+            .iter()
+            .filter(|slot| slot.bound().is_some() && slot.age() == self.age)
+            .count();
+        (used * 1000 / sample.len()) as u16
+    }
+
+    fn index(&self, key: u64) -> usize {
+        ((key as u32 as u64 * self.buckets.len() as u64) >> 32) as usize
+    }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> TranspositionTable {
+        TranspositionTable::new(DEFAULT_HASH_MB)
+    }
+}
+
+impl fmt::Debug for TranspositionTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TranspositionTable")
+            .field("buckets", &self.buckets.len())
+            .field("age", &self.age)
+            .finish()
+    }
+}
+
+/// Mate scores are stored as distances from the stored node rather than from the root, so
+/// that they stay right when the position is reached at another ply.
+fn score_to_tt(score: i32, ply: usize) -> i32 {
+    if is_mate_score(score) {
+        score + score.signum() * ply as i32
+    } else {
+        score
+    }
+}
+
+fn score_from_tt(score: i32, ply: usize) -> i32 {
+    if is_mate_score(score) {
+        score - score.signum() * ply as i32
+    } else {
+        score
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::{
+        evaluation::MATE_SCORE,
+        utils::{_move::MoveFlag, enums::Square},
+    };
+
+    const KEY: u64 = 0x0123_4567_89AB_CDEF;
+
+    #[test]
+    fn test_store_and_probe() {
+        let mut table = TranspositionTable::new(1);
+        assert_eq!(table.buckets.len(), 1024 * 1024 / 24);
+        assert_eq!(table.probe(KEY, 0), None);
+
+        let best_move = PackedMove::new(Square::E2, Square::E4, MoveFlag::DoublePawnPush);
+        table.store(KEY, Some(best_move), -35, 6, Bound::Exact, 3);
+        assert_eq!(
+            table.probe(KEY, 5),
+            Some(TTEntry {
+                best_move: Some(best_move),
+                score: -35,
+                depth: 6,
+                bound: Bound::Exact,
+            })
+        );
+        // Same bucket, different position
+        assert_eq!(table.probe(KEY ^ 1 << 40, 5), None);
+
+        // A later visit without a best move keeps the old one
+        table.store(KEY, None, 50, 7, Bound::Lower, 3);
+        let entry = table.probe(KEY, 3).unwrap();
+        assert_eq!(entry.best_move, Some(best_move));
+        assert_eq!(entry.bound, Bound::Lower);
+
+        table.clear();
+        assert_eq!(table.probe(KEY, 0), None);
+    }
+
+    #[test]
+    fn test_mate_scores() {
+        let mut table = TranspositionTable::new(1);
+        // Mate in 5 plies from a node at ply 3 is mate in 2 plies from the node itself
+        table.store(KEY, None, MATE_SCORE - 5, 4, Bound::Exact, 3);
+        assert_eq!(table.probe(KEY, 3).unwrap().score, MATE_SCORE - 5);
+        assert_eq!(table.probe(KEY, 1).unwrap().score, MATE_SCORE - 3);
+
+        table.store(KEY, None, -MATE_SCORE + 8, 4, Bound::Upper, 6);
+        assert_eq!(table.probe(KEY, 2).unwrap().score, -MATE_SCORE + 4);
+    }
+
+    #[test]
+    fn test_replacement() {
+        let mut table = TranspositionTable::new(0);
+        assert_eq!(table.buckets.len(), 1);
+        let keys = [1 << 32, 2 << 32, 3 << 32];
+
+        table.store(keys[0], None, 10, 8, Bound::Exact, 0);
+        // Shallower results go to the always-replace slot, overwriting each other
+        table.store(keys[1], None, 20, 2, Bound::Exact, 0);
+        table.store(keys[2], None, 30, 3, Bound::Exact, 0);
+        assert_eq!(table.probe(keys[0], 0).unwrap().depth, 8);
+        assert_eq!(table.probe(keys[1], 0), None);
+        assert_eq!(table.probe(keys[2], 0).unwrap().depth, 3);
+
+        // Deeper results, and any result of a later search, take the depth-preferred slot
+        table.store(keys[1], None, 20, 9, Bound::Exact, 0);
+        assert_eq!(table.probe(keys[0], 0), None);
+        assert_eq!(table.probe(keys[1], 0).unwrap().depth, 9);
+        table.new_search();
+        table.store(keys[0], None, 10, 1, Bound::Exact, 0);
+        assert_eq!(table.probe(keys[0], 0).unwrap().depth, 1);
+        assert_eq!(table.probe(keys[1], 0), None);
+    }
+
+    #[test]
+    fn test_hashfull() {
+        let mut table = TranspositionTable::new(1);
+        assert_eq!(table.hashfull(), 0);
+        // One entry in each of the first 250 buckets, a quarter of the sampled slots
+        let bucket_count = table.buckets.len() as u64;
+        for index in 0..250u64 {
+            let key = (index << 32).div_ceil(bucket_count);
+            table.store(key, None, 0, 1, Bound::Exact, 0);
+        }
+        assert_eq!(table.hashfull(), 250);
+
+        // Entries of earlier searches do not count
+        table.new_search();
+        assert_eq!(table.hashfull(), 0);
+        table.prefetch(KEY);
+    }
+}
@@ -0,0 +1,544 @@
+use crate::{
+    bitboard::{Bitboard, FENParseMode, STARTING_POSITION_FEN},
+    engine::{send, Engine, Output, SearchEvent, SearchInfo, SearchLimits},
+    evaluation::{is_mate_score, MATE_SCORE},
+    outcome::PositionHistory,
+    transposition_table::DEFAULT_HASH_MB,
+    utils::errors::{FENParseError, UciError},
+};
+
+use std::{io::BufRead, sync::Arc, time::Duration};
+
+/// Largest transposition table the `Hash` option allows, in megabytes.
+const MAX_HASH_MB: usize = 65536;
+/// Parameters of `go` that `parse_go` reads.
+const GO_PARAMETERS: [&str; 10] = [
+    "depth",
+    "nodes",
+    "movetime",
+    "wtime",
+    "btime",
+    "winc",
+    "binc",
+    "movestogo",
+    "infinite",
+    "ponder",
+];
+
+/// State of the engine as driven by a GUI over the Universal Chess Interface.
+pub struct Uci {
+    output: Output,
+    bitboard: Bitboard,
+    history: PositionHistory,
+    engine: Engine,
+    /// The `UCI_Chess960` option: castling moves are sent and received as the king taking its
+    /// own rook.
+    chess960: bool,
+}
+
+impl Uci {
+    pub fn new(output: Output) -> Uci {
+        let bitboard = STARTING_POSITION_FEN.parse::<Bitboard>().unwrap();
+        Uci {
+            output,
+            bitboard,
+            history: PositionHistory::new(&bitboard),
+            engine: Engine::new(),
+            chess960: false,
+        }
+    }
+
+    /// Handle one line sent by the GUI. Returns `false` once the GUI asks the engine to quit.
+    /// Commands that cannot be carried out are answered with an `info string` and otherwise
+    /// ignored.
+    pub fn handle_command(&mut self, line: &str) -> bool {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let Some((&command, args)) = tokens.split_first() else {
+            return true;
+        };
+
+        let result = match command {
+            "uci" => {
+                self.send_id();
+                Ok(())
+            }
+            "isready" => {
+                send(&self.output, "readyok");
+                Ok(())
+            }
+            "ucinewgame" => {
+                self.engine.clear_hash();
+                self.set_position(&["startpos"])
+            }
+            "position" => {
+                self.engine.stop();
+                self.set_position(args)
+            }
+            "go" => self.go(args),
+            "stop" => {
+                self.engine.stop();
+                Ok(())
+            }
+            // The opponent played the move pondered on, so the pondering search has been
+            // thinking about the right position all along and goes on under the clock
+            "ponderhit" => {
+                self.engine.ponderhit();
+                Ok(())
+            }
+            "setoption" => self.set_option(args),
+            "debug" => Ok(()),
+            "quit" => {
+                self.engine.stop();
+                return false;
+            }
+            _ => Err(UciError::UnknownCommand(command.to_string())),
+        };
+
+        if let Err(error) = result {
+            send(&self.output, &format!("info string {}", error));
+        }
+        true
+    }
+
+    fn send_id(&self) {
+        send(
+            &self.output,
+            &format!("id name rChess {}", env!("CARGO_PKG_VERSION")),
+        );
+        send(&self.output, "id author the rChess developers");
+        send(
+            &self.output,
+            &format!(
+                "option name Hash type spin default {} min 1 max {}",
+                DEFAULT_HASH_MB, MAX_HASH_MB
+            ),
+        );
+        send(&self.output, "option name Ponder type check default false");
+        send(
+            &self.output,
+            "option name UCI_Chess960 type check default false",
+        );
+        send(&self.output, "uciok");
+    }
+
+    /// `position (startpos | fen <fen>) [moves <move>...]`
+    fn set_position(&mut self, args: &[&str]) -> Result<(), UciError> {
+        let moves_index = args
+            .iter()
+            .position(|&arg| arg == "moves")
+            .unwrap_or(args.len());
+        let mut bitboard = match args.first() {
+            Some(&"startpos") => STARTING_POSITION_FEN.parse::<Bitboard>().unwrap(),
+            Some(&"fen") => {
+                Bitboard::from_fen(&args[1..moves_index].join(" "), FENParseMode::Lenient)
+                    .map_err(UciError::InvalidFEN)?
+            }
+            _ => {
+                return Err(UciError::MissingArgument {
+                    after: "position".to_string(),
+                    expected: "startpos or fen".to_string(),
+                })
+            }
+        };
+        bitboard.chess960 |= self.chess960;
+        let errors = bitboard.validate();
+        if !errors.is_empty() {
+            return Err(UciError::InvalidFEN(FENParseError::InvalidPosition(errors)));
+        }
+
+        let mut history = PositionHistory::new(&bitboard);
+        for move_str in args.iter().skip(moves_index + 1) {
+            let _move = bitboard
+                .parse_uci_move(move_str)
+                .map_err(UciError::IllegalMove)?;
+            bitboard.make_move(_move);
+            history.push(&bitboard);
+        }
+
+        self.bitboard = bitboard;
+        self.history = history;
+        Ok(())
+    }
+
+    /// `setoption name <name> [value <value>]`. Option names are not case sensitive.
+    fn set_option(&mut self, args: &[&str]) -> Result<(), UciError> {
+        if args.first() != Some(&"name") {
+            return Err(UciError::MissingArgument {
+                after: "setoption".to_string(),
+                expected: "name".to_string(),
+            });
+        }
+        let value_index = args
+            .iter()
+            .position(|&arg| arg == "value")
+            .unwrap_or(args.len());
+        let name = args[1..value_index].join(" ");
+        let value = args.get(value_index + 1..).unwrap_or_default().join(" ");
+        let invalid_value = || UciError::InvalidValue {
+            name: name.clone(),
+            value: value.clone(),
+        };
+
+        match name.to_lowercase().as_str() {
+            "hash" => {
+                let size_mb = value
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|size_mb| (1..=MAX_HASH_MB).contains(size_mb))
+                    .ok_or_else(invalid_value)?;
+                self.engine.set_hash_size(size_mb);
+                Ok(())
+            }
+            "uci_chess960" => {
+                self.chess960 = value.parse().map_err(|_| invalid_value())?;
+                Ok(())
+            }
+            // Pondering needs nothing set up; the GUI decides when to use it
+            "ponder" => value
+                .parse::<bool>()
+                .map(|_| ())
+                .map_err(|_| invalid_value()),
+            _ => Err(UciError::UnknownOption(name)),
+        }
+    }
+
+    /// `go [ponder] [<limit> <value>...]`. A pondering search keeps its move until `ponderhit`
+    /// or `stop` and only starts its clock at `ponderhit`. The best move comes with the reply expected to it, for
+    /// the GUI to ponder on.
+    fn go(&mut self, args: &[&str]) -> Result<(), UciError> {
+        let limits = parse_go(args)?;
+        let output = Arc::clone(&self.output);
+        let mut pv = Vec::new();
+        self.engine.start(
+            self.bitboard,
+            self.history.clone(),
+            limits,
+            move |event| match event {
+                SearchEvent::Info(info) => {
+                    send(&output, &format_info(&info));
+                    pv = info.pv;
+                }
+                SearchEvent::BestMove(Some(_move)) => match pv.as_slice() {
+                    [first, reply, ..] if *first == _move => {
+                        send(&output, &format!("bestmove {} ponder {}", _move, reply))
+                    }
+                    _ => send(&output, &format!("bestmove {}", _move)),
+                },
+                SearchEvent::BestMove(None) => send(&output, "bestmove 0000"),
+            },
+        );
+        Ok(())
+    }
+}
+
+/// Read the arguments of `go`. Times are in milliseconds; negative clock times, which some GUIs
+/// send once a flag has fallen, count as zero. Unsupported parameters such as `searchmoves` and `mate` are skipped along with their arguments.
+pub fn parse_go(args: &[&str]) -> Result<SearchLimits, UciError> {
+    let mut limits = SearchLimits::default();
+    let mut args = args.iter().peekable();
+    while let Some(&name) = args.next() {
+        let mut value = || {
+            let value = args.next().ok_or_else(|| UciError::MissingArgument {
+                after: name.to_string(),
+                expected: "a number".to_string(),
+            })?;
+            value
+                .parse::<i64>()
+                .map(|value| value.max(0) as u64)
+                .map_err(|_| UciError::InvalidValue {
+                    name: name.to_string(),
+                    value: value.to_string(),
+                })
+        };
+
+        match name {
+            "depth" => limits.depth = Some(value()? as usize),
+            "nodes" => limits.nodes = Some(value()?),
+            "movetime" => limits.move_time = Some(Duration::from_millis(value()?)),
+            "wtime" => limits.white_time = Some(Duration::from_millis(value()?)),
+            "btime" => limits.black_time = Some(Duration::from_millis(value()?)),
+            "winc" => limits.white_increment = Duration::from_millis(value()?),
+            "binc" => limits.black_increment = Duration::from_millis(value()?),
+            "movestogo" => limits.moves_to_go = Some(value()? as u32),
+            "infinite" => limits.infinite = true,
+            "ponder" => limits.ponder = true,
+            _ => while args.next_if(|arg| !GO_PARAMETERS.contains(arg)).is_some() {},
+        }
+    }
+    Ok(limits)
+}
+
+/// The `info` line reporting `info`. Mate scores are given in moves, negative when the engine
+/// is getting mated.
+pub fn format_info(info: &SearchInfo) -> String {
+    let score = if is_mate_score(info.score) {
+        let moves = (MATE_SCORE - info.score.abs() + 1) / 2;
+        format!("mate {}", moves * info.score.signum())
+    } else {
+        format!("cp {}", info.score)
+    };
+    let pv: Vec<String> = info.pv.iter().map(|_move| _move.to_string()).collect();
+    format!(
+        "info depth {} score {} nodes {} nps {} time {} hashfull {} pv {}",
+        info.depth,
+        score,
+        info.nodes,
+        info.nps(),
+        info.elapsed.as_millis(),
+        info.hashfull,
+        pv.join(" ")
+    )
+}
+
+/// Speak UCI over `input` and `output` until the GUI quits or closes `input`.
+pub fn run<R: BufRead>(input: R, output: Output) {
+    let mut uci = Uci::new(output);
+    for line in input.lines() {
+        let Ok(line) = line else {
+            return;
+        };
+        if !uci.handle_command(&line) {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::utils::_move::Move;
+    use std::{sync::Mutex, thread, time::Instant};
+
+    /// Send each line of `commands`, letting any search it starts finish, and return the lines
+    /// written.
+    fn run_session(commands: &str) -> Vec<String> {
+        let buffer = Arc::new(Mutex::new(Vec::new()));
+        let mut uci = Uci::new(buffer.clone());
+        for line in commands.lines() {
+            uci.handle_command(line);
+            uci.engine.wait();
+        }
+        let output = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        output.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_parse_go() {
+        assert_eq!(
+            parse_go(&[
+                "wtime",
+                "60000",
+                "btime",
+                "-20",
+                "winc",
+                "1000",
+                "movestogo",
+                "5"
+            ]),
+            Ok(SearchLimits {
+                white_time: Some(Duration::from_secs(60)),
+                black_time: Some(Duration::ZERO),
+                white_increment: Duration::from_secs(1),
+                moves_to_go: Some(5),
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            parse_go(&["depth", "6", "nodes", "10000", "movetime", "500"]),
+            Ok(SearchLimits {
+                depth: Some(6),
+                nodes: Some(10000),
+                move_time: Some(Duration::from_millis(500)),
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            parse_go(&["infinite"]).map(|limits| limits.infinite),
+            Ok(true)
+        );
+        assert_eq!(
+            parse_go(&["ponder", "wtime", "1000"]),
+            Ok(SearchLimits {
+                white_time: Some(Duration::from_millis(1000)),
+                ponder: true,
+                ..Default::default()
+            })
+        );
+        assert!(matches!(
+            parse_go(&["depth"]),
+            Err(UciError::MissingArgument { .. })
+        ));
+        assert!(matches!(
+            parse_go(&["depth", "six"]),
+            Err(UciError::InvalidValue { .. })
+        ));
+
+        // Unsupported parameters are skipped with their arguments
+        assert_eq!(
+            parse_go(&["searchmoves", "e2e4", "d2d4", "depth", "3", "mate", "2"]),
+            Ok(SearchLimits {
+                depth: Some(3),
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn test_format_info() {
+        let mut info = SearchInfo {
+            depth: 3,
+            score: -25,
+            nodes: 5000,
+            elapsed: Duration::from_millis(250),
+            pv: vec!["e2e4".parse().unwrap(), "e7e5".parse().unwrap()],
+            hashfull: 12,
+        };
+        assert_eq!(
+            format_info(&info),
+            "info depth 3 score cp -25 nodes 5000 nps 20000 time 250 hashfull 12 pv e2e4 e7e5"
+        );
+        info.score = MATE_SCORE - 3;
+        assert!(format_info(&info).contains("score mate 2 "));
+        info.score = -MATE_SCORE + 4;
+        assert!(format_info(&info).contains("score mate -2 "));
+    }
+
+    #[test]
+    fn test_set_position() {
+        let mut uci = Uci::new(Arc::new(Mutex::new(Vec::new())));
+        assert!(uci.handle_command("position startpos moves e2e4 e7e5 g1f3"));
+        assert_eq!(
+            uci.bitboard.to_str(),
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"
+        );
+
+        uci.handle_command("position fen 4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1 moves e1g1");
+        assert_eq!(uci.bitboard.to_str(), "4k3/8/8/8/8/8/8/R4RK1 b - - 1 1");
+
+        // With UCI_Chess960 castling is the king taking the rook
+        uci.handle_command("setoption name UCI_Chess960 value true");
+        uci.handle_command("position fen 4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1 moves e1h1");
+        assert_eq!(uci.bitboard.to_str(), "4k3/8/8/8/8/8/8/R4RK1 b - - 1 1");
+    }
+
+    #[test]
+    fn test_uci_session() {
+        let output = run_session(
+            "uci
+isready
+position startpos moves e2e4
+frobnicate
+position startpos moves e2e5
+setoption name Hash value 0
+setoption name Hash value 2
+go depth 1
+quit
+",
+        );
+        assert_eq!(
+            output[0],
+            format!("id name rChess {}", env!("CARGO_PKG_VERSION"))
+        );
+        assert!(output.contains(&"uciok".to_string()));
+        assert!(output.contains(&"readyok".to_string()));
+        assert!(output.contains(&"info string Unknown command frobnicate.".to_string()));
+        assert!(output
+            .iter()
+            .any(|line| line.starts_with("info string Move e2e5 is not legal")));
+        assert!(output.contains(&"info string Invalid value 0 for Hash.".to_string()));
+        assert!(
+            output.contains(&"option name Hash type spin default 16 min 1 max 65536".to_string())
+        );
+        assert!(output
+            .iter()
+            .any(|line| line.starts_with("info depth 1 score cp")));
+
+        // The illegal move left the position after 1. e4 in place
+        let best_move = output.last().unwrap().strip_prefix("bestmove ").unwrap();
+        let mut bitboard = STARTING_POSITION_FEN.parse::<Bitboard>().unwrap();
+        bitboard.make_move("e2e4".parse::<Move>().unwrap());
+        assert!(bitboard.parse_uci_move(best_move).is_ok());
+
+        let output = run_session("position fen R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1\ngo\n");
+        assert_eq!(output, vec!["bestmove 0000"]);
+
+        // Impossible positions are refused and the previous one kept
+        let output = run_session(
+            "position fen 6k1/8/8/8/8/8/8/R5K1 w - - 0 1
+position fen 8/8/8/8/8/8/8/8 w - - 0 1
+position fen P5k1/8/8/8/8/8/8/6K1 w - - 0 1
+go depth 2
+",
+        );
+        assert_eq!(
+            output[..2],
+            [
+                "info string FEN describes an impossible position: \
+                 [IncorrectKingCount(White, 0), IncorrectKingCount(Black, 0)].",
+                "info string FEN describes an impossible position: [PawnOnBackRank(A8)].",
+            ]
+        );
+        let bitboard = "6k1/8/8/8/8/8/8/R5K1 w - - 0 1"
+            .parse::<Bitboard>()
+            .unwrap();
+        let best_move = output.last().unwrap().split_whitespace().nth(1).unwrap();
+        assert!(bitboard.parse_uci_move(best_move).is_ok());
+    }
+
+    #[test]
+    fn test_ponder() {
+        let buffer = Arc::new(Mutex::new(Vec::new()));
+        let mut uci = Uci::new(buffer.clone());
+        let bestmoves = || {
+            String::from_utf8_lossy(&buffer.lock().unwrap())
+                .lines()
+                .filter(|line| line.starts_with("bestmove"))
+                .map(String::from)
+                .collect::<Vec<_>>()
+        };
+
+        // The best move comes with the expected reply to ponder on
+        uci.handle_command("position startpos");
+        uci.handle_command("go depth 3");
+        uci.engine.wait();
+        let tokens: Vec<String> = bestmoves()[0]
+            .split_whitespace()
+            .map(String::from)
+            .collect();
+        assert_eq!(tokens.len(), 4);
+        assert_eq!(tokens[2], "ponder");
+        let mut bitboard = uci.bitboard;
+        bitboard.make_move(bitboard.parse_uci_move(&tokens[1]).unwrap());
+        assert!(bitboard.parse_uci_move(&tokens[3]).is_ok());
+
+        // A pondering search keeps its move until the expected reply is played, whatever its
+        // limits
+        uci.handle_command("position startpos moves e2e4 e7e5");
+        uci.handle_command("go ponder depth 1 wtime 1000 btime 1000");
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(bestmoves().len(), 1);
+        uci.handle_command("ponderhit");
+        uci.engine.wait();
+        assert_eq!(bestmoves().len(), 2);
+
+        // The clock of the pondering search only runs from ponderhit on
+        uci.handle_command("go ponder wtime 300 btime 300 movestogo 1");
+        thread::sleep(Duration::from_millis(400));
+        assert_eq!(bestmoves().len(), 2);
+        let ponderhit = Instant::now();
+        uci.handle_command("ponderhit");
+        uci.engine.wait();
+        assert!(ponderhit.elapsed() < Duration::from_millis(1000));
+        assert_eq!(bestmoves().len(), 3);
+
+        uci.handle_command("go ponder depth 1");
+        uci.handle_command("stop");
+        assert_eq!(bestmoves().len(), 4);
+
+        // Without pondering ponderhit does nothing
+        uci.handle_command("go depth 1");
+        uci.engine.wait();
+        uci.handle_command("ponderhit");
+        assert_eq!(bestmoves().len(), 5);
+    }
+}
@@ -1,5 +1,9 @@
 use super::enums::{Piece, Square};
+use super::errors::MoveParseError;
+use crate::bitboard::Bitboard;
+use int_enum::IntEnum;
 use std::fmt::{self};
+use std::str::FromStr;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Move {
@@ -18,12 +22,284 @@ impl fmt::Display for Move {
             self.dest.to_string().to_lowercase()
         )?;
         match self.promotion {
-            Some(Piece::Queen) => writeln!(f, "q")?,
-            Some(Piece::Rook) => writeln!(f, "r")?,
-            Some(Piece::Bishop) => writeln!(f, "b")?,
-            Some(Piece::Knight) => writeln!(f, "n")?,
-            _default => writeln!(f, "")?,
+            Some(Piece::Queen) => write!(f, "q")?,
+            Some(Piece::Rook) => write!(f, "r")?,
+            Some(Piece::Bishop) => write!(f, "b")?,
+            Some(Piece::Knight) => write!(f, "n")?,
+            _default => {}
         }
         Ok(())
     }
 }
+
+/// Parse a move in UCI long algebraic notation such as `e2e4` or `e7e8q`. Only the shape of the
+/// move is checked; use `Bitboard::parse_uci_move` to also check it against a position.
+impl FromStr for Move {
+    type Err = MoveParseError;
+
+    fn from_str(move_str: &str) -> Result<Move, MoveParseError> {
+        if !move_str.is_ascii() || !(4..=5).contains(&move_str.len()) {
+            return Err(MoveParseError::IncorrectLength(move_str.chars().count()));
+        }
+
+        let parse_square = |square_str: &str| {
+            square_str
+                .to_ascii_uppercase()
+                .parse::<Square>()
+                .map_err(|_| MoveParseError::IncorrectSquare(square_str.to_string()))
+        };
+        let orig = parse_square(&move_str[0..2])?;
+        let dest = parse_square(&move_str[2..4])?;
+
+        let promotion = match move_str[4..].chars().next() {
+            None => None,
+            Some('q' | 'Q') => Some(Piece::Queen),
+            Some('r' | 'R') => Some(Piece::Rook),
+            Some('b' | 'B') => Some(Piece::Bishop),
+            Some('n' | 'N') => Some(Piece::Knight),
+            Some(other) => return Err(MoveParseError::IncorrectPromotionPiece(other)),
+        };
+
+        Ok(Move {
+            orig,
+            dest,
+            promotion,
+        })
+    }
+}
+
+/// Kind of a move, as stored in the top four bits of a `PackedMove`. Promotions keep the
+/// promoted piece in their two lowest bits and captures set bit 2.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, IntEnum)]
+#[repr(u8)]
+pub enum MoveFlag {
+    Quiet = 0,
+    DoublePawnPush = 1,
+    KingsideCastle = 2,
+    QueensideCastle = 3,
+    Capture = 4,
+    EnPassant = 5,
+    KnightPromotion = 8,
+    BishopPromotion = 9,
+    RookPromotion = 10,
+    QueenPromotion = 11,
+    KnightPromotionCapture = 12,
+    BishopPromotionCapture = 13,
+    RookPromotionCapture = 14,
+    QueenPromotionCapture = 15,
+}
+
+const CAPTURE_FLAG_BIT: u8 = 0b0100;
+const PROMOTION_FLAG_BIT: u8 = 0b1000;
+
+/// A move packed into 16 bits: origin in bits 0-5, destination in bits 6-11 and a `MoveFlag`
+/// in bits 12-15. Small enough for transposition tables and cheap move lists.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub struct PackedMove(pub u16);
+
+impl PackedMove {
+    pub fn new(orig: Square, dest: Square, flag: MoveFlag) -> PackedMove {
+        PackedMove(orig as u16 | (dest as u16) << 6 | (flag as u16) << 12)
+    }
+
+    /// Pack `_move` as played in `bitboard`, which is needed to tell captures, en passant,
+    /// double pushes and castling apart.
+    pub fn from_move(bitboard: &Bitboard, _move: &Move) -> PackedMove {
+        let move_piece = bitboard.get_piece_at(_move.orig).map(|(_, piece)| piece);
+        let is_capture = bitboard.get_color_pieces(bitboard.to_move.opposite()).0
+            & (1 << _move.dest as usize)
+            != 0;
+        let distance = _move.dest as i8 - _move.orig as i8;
+
+        let flag = if let Some(piece) = _move.promotion {
+            let piece_bits = match piece {
+                Piece::Bishop => 1,
+                Piece::Rook => 2,
+                Piece::Queen => 3,
+                _ => 0,
+            };
+            let capture_bit = if is_capture { CAPTURE_FLAG_BIT } else { 0 };
+            MoveFlag::from_int(PROMOTION_FLAG_BIT | capture_bit | piece_bits).unwrap()
+        } else if let Some(cm) = bitboard.get_castle(_move) {
+            if cm.is_kingside() {
+                MoveFlag::KingsideCastle
+            } else {
+                MoveFlag::QueensideCastle
+            }
+        } else if move_piece == Some(Piece::Pawn) && distance.abs() == 16 {
+            MoveFlag::DoublePawnPush
+        } else if move_piece == Some(Piece::Pawn)
+            && !is_capture
+            && bitboard.en_passant_square == Some(_move.dest)
+        {
+            MoveFlag::EnPassant
+        } else if is_capture {
+            MoveFlag::Capture
+        } else {
+            MoveFlag::Quiet
+        };
+
+        PackedMove::new(_move.orig, _move.dest, flag)
+    }
+
+    pub fn orig(&self) -> Square {
+        Square::from_int((self.0 & 0x3F) as u8).unwrap()
+    }
+
+    pub fn dest(&self) -> Square {
+        Square::from_int(((self.0 >> 6) & 0x3F) as u8).unwrap()
+    }
+
+    pub fn flag(&self) -> MoveFlag {
+        MoveFlag::from_int((self.0 >> 12) as u8).unwrap_or(MoveFlag::Quiet)
+    }
+
+    pub fn is_capture(&self) -> bool {
+        self.flag() == MoveFlag::EnPassant || (self.0 >> 12) as u8 & CAPTURE_FLAG_BIT != 0
+    }
+
+    pub fn is_castle(&self) -> bool {
+        matches!(
+            self.flag(),
+            MoveFlag::KingsideCastle | MoveFlag::QueensideCastle
+        )
+    }
+
+    pub fn promotion(&self) -> Option<Piece> {
+        let flag = (self.0 >> 12) as u8;
+        if flag & PROMOTION_FLAG_BIT == 0 {
+            return None;
+        }
+        Some(match flag & 0b11 {
+            0 => Piece::Knight,
+            1 => Piece::Bishop,
+            2 => Piece::Rook,
+            _ => Piece::Queen,
+        })
+    }
+}
+
+impl From<PackedMove> for Move {
+    fn from(packed_move: PackedMove) -> Move {
+        Move {
+            orig: packed_move.orig(),
+            dest: packed_move.dest(),
+            promotion: packed_move.promotion(),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn test_packed_move_round_trip() {
+        let bitboard = "r3k2r/1P6/8/3pP3/8/8/4P3/R3K2R w KQkq d6 0 1"
+            .parse::<Bitboard>()
+            .unwrap();
+        let cases = [
+            (Square::E2, Square::E3, None, MoveFlag::Quiet),
+            (Square::E2, Square::E4, None, MoveFlag::DoublePawnPush),
+            (Square::E1, Square::G1, None, MoveFlag::KingsideCastle),
+            (Square::E1, Square::C1, None, MoveFlag::QueensideCastle),
+            (Square::A1, Square::A8, None, MoveFlag::Capture),
+            (Square::E5, Square::D6, None, MoveFlag::EnPassant),
+            (
+                Square::B7,
+                Square::B8,
+                Some(Piece::Knight),
+                MoveFlag::KnightPromotion,
+            ),
+            (
+                Square::B7,
+                Square::B8,
+                Some(Piece::Queen),
+                MoveFlag::QueenPromotion,
+            ),
+            (
+                Square::B7,
+                Square::A8,
+                Some(Piece::Rook),
+                MoveFlag::RookPromotionCapture,
+            ),
+            (
+                Square::B7,
+                Square::A8,
+                Some(Piece::Bishop),
+                MoveFlag::BishopPromotionCapture,
+            ),
+        ];
+
+        for (orig, dest, promotion, flag) in cases {
+            let _move = Move {
+                orig,
+                dest,
+                promotion,
+            };
+            let packed_move = PackedMove::from_move(&bitboard, &_move);
+            assert_eq!(packed_move.flag(), flag);
+            assert_eq!(packed_move.orig(), orig);
+            assert_eq!(packed_move.dest(), dest);
+            assert_eq!(Move::from(packed_move), _move);
+        }
+
+        assert!(PackedMove::new(Square::E5, Square::D6, MoveFlag::EnPassant).is_capture());
+        assert!(!PackedMove::new(Square::B7, Square::B8, MoveFlag::QueenPromotion).is_capture());
+        assert!(PackedMove::new(Square::E1, Square::C1, MoveFlag::QueensideCastle).is_castle());
+        assert_eq!(std::mem::size_of::<PackedMove>(), 2);
+    }
+
+    #[test]
+    fn test_display() {
+        let _move = Move {
+            orig: Square::E7,
+            dest: Square::E8,
+            promotion: Some(Piece::Queen),
+        };
+        assert_eq!(_move.to_string(), "e7e8q");
+
+        let _move = Move {
+            orig: Square::G1,
+            dest: Square::F3,
+            promotion: None,
+        };
+        assert_eq!(_move.to_string(), "g1f3");
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!(
+            "e7e8q".parse::<Move>(),
+            Ok(Move {
+                orig: Square::E7,
+                dest: Square::E8,
+                promotion: Some(Piece::Queen),
+            })
+        );
+        assert_eq!(
+            "a2a1N".parse::<Move>(),
+            Ok(Move {
+                orig: Square::A2,
+                dest: Square::A1,
+                promotion: Some(Piece::Knight),
+            })
+        );
+        for move_str in ["e2e4", "h7h8r", "b2c1b"] {
+            assert_eq!(move_str.parse::<Move>().unwrap().to_string(), move_str);
+        }
+
+        assert_eq!(
+            "e2e".parse::<Move>(),
+            Err(MoveParseError::IncorrectLength(3))
+        );
+        assert_eq!(
+            "e2i4".parse::<Move>(),
+            Err(MoveParseError::IncorrectSquare(String::from("i4")))
+        );
+        assert_eq!(
+            "e7e8k".parse::<Move>(),
+            Err(MoveParseError::IncorrectPromotionPiece('k'))
+        );
+    }
+}
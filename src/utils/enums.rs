@@ -1,7 +1,5 @@
-use std::usize;
-
 use int_enum::IntEnum;
-use strum_macros::{Display, EnumIter, EnumString, ToString};
+use strum_macros::{Display, EnumIter, EnumString};
 
 use super::board_slice::BoardSlice;
 
@@ -38,7 +36,37 @@ pub enum CastleMoves {
     BlackQueensideCastle = 0b1000,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter, EnumString, IntEnum, ToString)]
+impl CastleMoves {
+    pub fn new(color: Color, kingside: bool) -> CastleMoves {
+        match (color, kingside) {
+            (Color::White, true) => CastleMoves::WhiteKingsideCastle,
+            (Color::White, false) => CastleMoves::WhiteQueensideCastle,
+            (Color::Black, true) => CastleMoves::BlackKingsideCastle,
+            (Color::Black, false) => CastleMoves::BlackQueensideCastle,
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            CastleMoves::WhiteKingsideCastle | CastleMoves::WhiteQueensideCastle => Color::White,
+            CastleMoves::BlackKingsideCastle | CastleMoves::BlackQueensideCastle => Color::Black,
+        }
+    }
+
+    pub fn is_kingside(&self) -> bool {
+        matches!(
+            self,
+            CastleMoves::WhiteKingsideCastle | CastleMoves::BlackKingsideCastle
+        )
+    }
+
+    /// Position of the right in the castling rights mask, for per-right tables.
+    pub fn index(&self) -> usize {
+        (*self as u8).trailing_zeros() as usize
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter, EnumString, IntEnum, Display)]
 #[repr(u8)]
 pub enum Square {
     A1 = 0,
@@ -138,5 +166,5 @@ pub enum Rank {
 }
 
 pub fn rank_mask(rank: Rank) -> BoardSlice {
-    BoardSlice(0xFF << 8 * rank as usize)
+    BoardSlice(0xFF << (8 * rank as usize))
 }
@@ -1,25 +1,82 @@
 use thiserror::Error;
 
+use super::{
+    _move::Move,
+    enums::{CastleMoves, Color, Piece, Square},
+};
+
 #[derive(Debug, Error, PartialEq)]
 pub enum FENParseError {
-    #[error("FEN does not have 6 parts; instead has {0} parts.")]
+    #[error("FEN has the wrong number of fields: {0}.")]
     IncorrectPartsCount(usize),
-    #[error("Board does not have 8 parts; instead has {0} parts.")]
-    IncorrectBoardLength(usize),
-    #[error("Row {0} is too long.")]
-    IncorrectBoardRowLength(usize),
-    #[error("Incorrect symbol found in board string: {0}.")]
-    IncorrectBoard(char),
-    #[error("Incorrect symbol found in color to move.")]
-    IncorrectToMove,
-    #[error("Incorrect symbol found in castling rights.")]
-    IncorrectCastlingRights,
-    #[error("Incorrect symbol found in color to move.")]
-    IncorrectEnPassantSquare,
-    #[error("Incorrect symbol found in half move clock.")]
-    IncorrectHalfMoveClock,
-    #[error("Incorrect symbol found in full move clock.")]
-    IncorrectFullMoveClock,
+    #[error("Board does not have 8 ranks; instead has {ranks} (field {field}, offset {offset}).")]
+    IncorrectBoardLength {
+        field: usize,
+        offset: usize,
+        ranks: usize,
+    },
+    #[error("Rank {rank} does not have 8 squares (field {field}, offset {offset}).")]
+    IncorrectBoardRowLength {
+        field: usize,
+        offset: usize,
+        rank: usize,
+    },
+    #[error("Incorrect symbol {symbol} found in board (field {field}, offset {offset}).")]
+    IncorrectBoard {
+        field: usize,
+        offset: usize,
+        symbol: char,
+    },
+    #[error("Incorrect color to move {found} (field {field}, offset {offset}).")]
+    IncorrectToMove {
+        field: usize,
+        offset: usize,
+        found: String,
+    },
+    #[error(
+        "Incorrect symbol {symbol} found in castling rights (field {field}, offset {offset})."
+    )]
+    IncorrectCastlingRights {
+        field: usize,
+        offset: usize,
+        symbol: char,
+    },
+    #[error("Incorrect en passant square {found} (field {field}, offset {offset}).")]
+    IncorrectEnPassantSquare {
+        field: usize,
+        offset: usize,
+        found: String,
+    },
+    #[error("Incorrect half move clock {found} (field {field}, offset {offset}).")]
+    IncorrectHalfMoveClock {
+        field: usize,
+        offset: usize,
+        found: String,
+    },
+    #[error("Incorrect full move clock {found} (field {field}, offset {offset}).")]
+    IncorrectFullMoveClock {
+        field: usize,
+        offset: usize,
+        found: String,
+    },
+    #[error("FEN describes an impossible position: {0:?}.")]
+    InvalidPosition(Vec<PositionError>),
+}
+
+#[derive(Debug, Error, PartialEq, Eq, Clone)]
+pub enum PositionError {
+    #[error("{0} has {1} kings instead of one.")]
+    IncorrectKingCount(Color, u32),
+    #[error("Pawn found on back rank square {0}.")]
+    PawnOnBackRank(Square),
+    #[error("More than one piece found on square {0}.")]
+    OverlappingPieces(Square),
+    #[error("En passant square {0} does not follow a double pawn push.")]
+    InvalidEnPassantSquare(Square),
+    #[error("Castling right {0:?} without king and rook on their home squares.")]
+    InvalidCastlingRights(CastleMoves),
+    #[error("The side not to move is in check.")]
+    OpponentInCheck,
 }
 
 #[derive(Debug, Error, PartialEq)]
@@ -36,4 +93,146 @@ pub enum PerformMoveError {
     EnPassantImpossible,
     #[error("Impossible double push move.")]
     ImpossibleDoublePush,
+    #[error("No piece found on origin square {0}.")]
+    NoPieceOnOrigin(Square),
+    #[error("Piece moved belongs to the side not to move.")]
+    WrongSideToMove,
+    #[error("Destination square {0} holds a piece of the moving side.")]
+    CaptureOwnPiece(Square),
+    #[error("Pawn cannot promote to {0:?}.")]
+    InvalidPromotionPiece(Piece),
+    #[error("Promotion given for a move that is not a pawn reaching the last rank.")]
+    IllegalPromotion,
+    #[error("Pawn reaching the last rank must promote.")]
+    MissingPromotion,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum MoveParseError {
+    #[error("Move must be 4 or 5 characters long; instead has {0} characters.")]
+    IncorrectLength(usize),
+    #[error("Incorrect square found in move: {0}.")]
+    IncorrectSquare(String),
+    #[error("Incorrect promotion piece found in move: {0}.")]
+    IncorrectPromotionPiece(char),
+    #[error("Move {0} is not legal in this position.")]
+    IllegalMove(Move),
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum SANParseError {
+    #[error("Incorrect SAN syntax: {0}.")]
+    InvalidSyntax(String),
+    #[error("SAN move {0} is not legal in this position.")]
+    IllegalMove(String),
+    #[error("SAN move {0} matches more than one legal move.")]
+    AmbiguousMove(String),
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum PGNParseError {
+    #[error("Game {game}, line {line}: malformed tag pair {token}.")]
+    MalformedTag {
+        game: usize,
+        line: usize,
+        token: String,
+    },
+    #[error("Game {game}, line {line}: comment is never closed.")]
+    UnterminatedComment { game: usize, line: usize },
+    #[error("Game {game}, line {line}: unexpected token {token}.")]
+    UnexpectedToken {
+        game: usize,
+        line: usize,
+        token: String,
+    },
+    #[error("Game {game}, line {line}: movetext ends inside a variation.")]
+    UnterminatedVariation { game: usize, line: usize },
+    #[error("Game {game}, line {line}: cannot play move {token}: {error}")]
+    InvalidMove {
+        game: usize,
+        line: usize,
+        token: String,
+        error: SANParseError,
+    },
+    #[error("Game {game}, line {line}: invalid FEN tag {token}: {error}")]
+    InvalidFEN {
+        game: usize,
+        line: usize,
+        token: String,
+        error: FENParseError,
+    },
+    #[error("Could not read PGN input: {0}.")]
+    Io(String),
+    #[error("No game found in PGN input.")]
+    NoGame,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum GameTreeError {
+    #[error("No node with id {0} in the game tree.")]
+    NodeNotFound(usize),
+    #[error("The root node has no move and cannot be changed.")]
+    RootNode,
+    #[error("Move {0} is not legal in this position.")]
+    IllegalMove(Move),
+    #[error("{0}")]
+    PGNParse(PGNParseError),
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum EPDParseError {
+    #[error("{0}")]
+    InvalidFEN(FENParseError),
+    #[error("Opcode {0} is not a valid opcode.")]
+    InvalidOpcode(String),
+    #[error("Opcode {opcode}: cannot read move {token}: {error}")]
+    InvalidMove {
+        opcode: String,
+        token: String,
+        error: SANParseError,
+    },
+    #[error("Opcode {opcode}: {token} is not an integer.")]
+    InvalidInteger { opcode: String, token: String },
+    #[error("Opcode {0} has a string operand that is never closed.")]
+    UnterminatedString(String),
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum PolyglotError {
+    #[error("Book size {0} is not a multiple of the 16 byte entry size.")]
+    InvalidLength(usize),
+    #[error("Could not read Polyglot book: {0}.")]
+    Io(String),
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum UciError {
+    #[error("Unknown command {0}.")]
+    UnknownCommand(String),
+    #[error("Expected {expected} after {after}.")]
+    MissingArgument { after: String, expected: String },
+    #[error("Invalid value {value} for {name}.")]
+    InvalidValue { name: String, value: String },
+    #[error("Unknown option {0}.")]
+    UnknownOption(String),
+    #[error("{0}")]
+    InvalidFEN(FENParseError),
+    #[error("{0}")]
+    IllegalMove(MoveParseError),
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum XBoardError {
+    #[error("unknown command")]
+    UnknownCommand,
+    #[error("missing argument")]
+    MissingArgument,
+    #[error("invalid argument {0}")]
+    InvalidArgument(String),
+    #[error("{0}")]
+    InvalidFEN(FENParseError),
+    #[error("no move to undo")]
+    NothingToUndo,
+    #[error("Illegal move: {0}")]
+    IllegalMove(String),
 }
@@ -0,0 +1,547 @@
+use crate::{
+    bitboard::{Bitboard, STARTING_POSITION_FEN},
+    engine::{send, Engine, Output, SearchEvent, SearchInfo, SearchLimits},
+    evaluation::{is_mate_score, MATE_SCORE},
+    move_performing::UndoInfo,
+    outcome::{PositionHistory, Termination},
+    utils::{_move::Move, enums::Color, errors::XBoardError},
+};
+
+use std::{
+    io::BufRead,
+    sync::{
+        mpsc::{self, Sender},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+/// Time control until the GUI sends `level`: 40 moves in 5 minutes.
+const DEFAULT_MOVES_PER_SESSION: u32 = 40;
+const DEFAULT_BASE_TIME: Duration = Duration::from_secs(300);
+/// Mate scores are shown as 100000 plus the number of moves to mate, as XBoard expects.
+const XBOARD_MATE_SCORE: i32 = 100_000;
+
+/// What the XBoard front end reacts to: a command line from the GUI, or the move of a finished
+/// search.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Message {
+    Command(String),
+    BestMove {
+        search_id: usize,
+        best_move: Option<Move>,
+    },
+}
+
+/// State of the engine as driven by a GUI over the Chess Engine Communication Protocol. Unlike
+/// UCI, the engine keeps track of the game itself and plays its moves on its own board.
+pub struct XBoard {
+    output: Output,
+    sender: Sender<Message>,
+    engine: Engine,
+    bitboard: Bitboard,
+    history: PositionHistory,
+    undo_stack: Vec<(Move, UndoInfo)>,
+    /// Side the engine plays, `None` in force mode.
+    engine_color: Option<Color>,
+    post: bool,
+    /// Moves per time control, 0 when the whole game is played on one clock.
+    moves_per_session: u32,
+    base_time: Duration,
+    increment: Duration,
+    /// Fixed time per move set with `st`.
+    move_time: Option<Duration>,
+    max_depth: Option<usize>,
+    time: Duration,
+    opponent_time: Duration,
+    /// Identifies the running search. Bumped when a search is cut short so that its move is
+    /// discarded.
+    search_id: usize,
+}
+
+impl XBoard {
+    /// Front end writing to `output`. Best moves are sent back through `sender`, to be handed to
+    /// `handle_message` with the commands.
+    pub fn new(output: Output, sender: Sender<Message>) -> XBoard {
+        let bitboard = STARTING_POSITION_FEN.parse::<Bitboard>().unwrap();
+        XBoard {
+            output,
+            sender,
+            engine: Engine::new(),
+            bitboard,
+            history: PositionHistory::new(&bitboard),
+            undo_stack: Vec::new(),
+            engine_color: Some(Color::Black),
+            post: false,
+            moves_per_session: DEFAULT_MOVES_PER_SESSION,
+            base_time: DEFAULT_BASE_TIME,
+            increment: Duration::ZERO,
+            move_time: None,
+            max_depth: None,
+            time: DEFAULT_BASE_TIME,
+            opponent_time: DEFAULT_BASE_TIME,
+            search_id: 0,
+        }
+    }
+
+    /// Returns `false` once the GUI asks the engine to quit.
+    pub fn handle_message(&mut self, message: Message) -> bool {
+        match message {
+            Message::Command(line) => return self.handle_command(&line),
+            Message::BestMove {
+                search_id,
+                best_move: Some(best_move),
+            } if search_id == self.search_id => {
+                self.play_move(best_move);
+                send(&self.output, &format!("move {}", best_move));
+                self.report_outcome();
+            }
+            Message::BestMove { .. } => {}
+        }
+        true
+    }
+
+    /// Handle one line sent by the GUI. Returns `false` once the GUI asks the engine to quit.
+    /// Commands that cannot be carried out are answered with an error and otherwise ignored.
+    pub fn handle_command(&mut self, line: &str) -> bool {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let Some((&command, args)) = tokens.split_first() else {
+            return true;
+        };
+
+        let result = match command {
+            "protover" => {
+                send(
+                    &self.output,
+                    &format!(
+                        "feature myname=\"rChess {}\" ping=1 setboard=1 usermove=1 sigint=0 \
+                         sigterm=0 colors=0 analyze=0 variants=\"normal\" done=1",
+                        env!("CARGO_PKG_VERSION")
+                    ),
+                );
+                Ok(())
+            }
+            "new" => {
+                self.stop_thinking();
+                self.engine.clear_hash();
+                self.set_board(STARTING_POSITION_FEN.parse().unwrap());
+                self.engine_color = Some(Color::Black);
+                self.max_depth = None;
+                self.time = self.base_time;
+                self.opponent_time = self.base_time;
+                Ok(())
+            }
+            "force" => {
+                self.stop_thinking();
+                self.engine_color = None;
+                Ok(())
+            }
+            "go" => {
+                self.engine_color = Some(self.bitboard.to_move);
+                self.think();
+                Ok(())
+            }
+            "usermove" => self.user_move(args),
+            "setboard" => {
+                self.stop_thinking();
+                args.join(" ")
+                    .parse::<Bitboard>()
+                    .map(|bitboard| self.set_board(bitboard))
+                    .map_err(XBoardError::InvalidFEN)
+            }
+            "undo" => {
+                self.stop_thinking();
+                self.undo_moves(1)
+            }
+            "remove" => {
+                self.stop_thinking();
+                self.undo_moves(2)
+            }
+            "level" => self.set_level(args),
+            "st" => parse_arg::<u64>(args).map(|seconds| {
+                self.move_time = Some(Duration::from_secs(seconds));
+            }),
+            "sd" => parse_arg::<usize>(args).map(|depth| self.max_depth = Some(depth)),
+            "time" => parse_arg::<u64>(args).map(|centiseconds| {
+                self.time = Duration::from_millis(10 * centiseconds);
+            }),
+            "otim" => parse_arg::<u64>(args).map(|centiseconds| {
+                self.opponent_time = Duration::from_millis(10 * centiseconds);
+            }),
+            "post" => {
+                self.post = true;
+                Ok(())
+            }
+            "nopost" => {
+                self.post = false;
+                Ok(())
+            }
+            "?" => {
+                // Stopping keeps the search id, so the move found so far is played
+                self.engine.stop();
+                Ok(())
+            }
+            "ping" => {
+                send(&self.output, &format!("pong {}", args.join(" ")));
+                Ok(())
+            }
+            "result" => {
+                self.stop_thinking();
+                self.engine_color = None;
+                Ok(())
+            }
+            "xboard" | "accepted" | "rejected" | "random" | "hard" | "easy" | "computer"
+            | "name" | "rating" | "ics" => Ok(()),
+            "quit" => {
+                self.stop_thinking();
+                return false;
+            }
+            _ => Err(XBoardError::UnknownCommand),
+        };
+
+        match result {
+            Err(error @ XBoardError::IllegalMove(_)) => send(&self.output, &error.to_string()),
+            Err(error) => send(&self.output, &format!("Error ({}): {}", error, line)),
+            Ok(()) => {}
+        }
+        true
+    }
+
+    fn set_board(&mut self, bitboard: Bitboard) {
+        self.bitboard = bitboard;
+        self.history = PositionHistory::new(&bitboard);
+        self.undo_stack.clear();
+    }
+
+    fn play_move(&mut self, _move: Move) {
+        let undo_info = self.bitboard.make_move(_move);
+        self.history.push(&self.bitboard);
+        self.undo_stack.push((_move, undo_info));
+    }
+
+    fn undo_moves(&mut self, count: usize) -> Result<(), XBoardError> {
+        if self.undo_stack.len() < count {
+            return Err(XBoardError::NothingToUndo);
+        }
+        for _ in 0..count {
+            let (_move, undo_info) = self.undo_stack.pop().unwrap();
+            self.bitboard.unmake_move(_move, undo_info);
+            self.history.pop();
+        }
+        Ok(())
+    }
+
+    /// `usermove <move>`: play the opponent's move and, unless the game is over, reply to it.
+    fn user_move(&mut self, args: &[&str]) -> Result<(), XBoardError> {
+        let move_str = args.first().ok_or(XBoardError::MissingArgument)?;
+        let _move = self
+            .bitboard
+            .parse_uci_move(move_str)
+            .map_err(|_| XBoardError::IllegalMove(move_str.to_string()))?;
+        self.play_move(_move);
+
+        if !self.report_outcome() && self.engine_color == Some(self.bitboard.to_move) {
+            self.think();
+        }
+        Ok(())
+    }
+
+    /// `level <moves per session> <minutes[:seconds]> <increment seconds>`
+    fn set_level(&mut self, args: &[&str]) -> Result<(), XBoardError> {
+        let [moves, base, increment] = args else {
+            return Err(XBoardError::MissingArgument);
+        };
+        let invalid = |arg: &str| XBoardError::InvalidArgument(arg.to_string());
+
+        self.moves_per_session = moves.parse().map_err(|_| invalid(moves))?;
+        let (minutes, seconds) = base.split_once(':').unwrap_or((base, "0"));
+        let base_seconds = minutes.parse::<u64>().map_err(|_| invalid(base))? * 60
+            + seconds.parse::<u64>().map_err(|_| invalid(base))?;
+        self.base_time = Duration::from_secs(base_seconds);
+        self.increment = increment
+            .parse::<f64>()
+            .ok()
+            .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+            .ok_or_else(|| invalid(increment))?;
+        self.move_time = None;
+        self.time = self.base_time;
+        self.opponent_time = self.base_time;
+        Ok(())
+    }
+
+    fn limits(&self, color: Color) -> SearchLimits {
+        let mut limits = SearchLimits {
+            depth: self.max_depth,
+            move_time: self.move_time,
+            ..Default::default()
+        };
+        if self.moves_per_session > 0 {
+            let moves_played =
+                (self.bitboard.full_move_clock as u32).saturating_sub(1) % self.moves_per_session;
+            limits.moves_to_go = Some(self.moves_per_session - moves_played);
+        }
+
+        let (own_time, own_increment, other_time, other_increment) = match color {
+            Color::White => (
+                &mut limits.white_time,
+                &mut limits.white_increment,
+                &mut limits.black_time,
+                &mut limits.black_increment,
+            ),
+            Color::Black => (
+                &mut limits.black_time,
+                &mut limits.black_increment,
+                &mut limits.white_time,
+                &mut limits.white_increment,
+            ),
+        };
+        *own_time = Some(self.time);
+        *own_increment = self.increment;
+        *other_time = Some(self.opponent_time);
+        *other_increment = self.increment;
+        limits
+    }
+
+    /// Start searching for the engine's move. The move comes back as a `Message::BestMove`.
+    fn think(&mut self) {
+        self.search_id += 1;
+        let search_id = self.search_id;
+        let sender = self.sender.clone();
+        let output = Arc::clone(&self.output);
+        let post = self.post;
+
+        self.engine.start(
+            self.bitboard,
+            self.history.clone(),
+            self.limits(self.bitboard.to_move),
+            move |event| match event {
+                SearchEvent::Info(info) if post => send(&output, &format_thinking(&info)),
+                SearchEvent::Info(_) => {}
+                SearchEvent::BestMove(best_move) => {
+                    // The receiver is only gone once the front end has quit
+                    let _ = sender.send(Message::BestMove {
+                        search_id,
+                        best_move,
+                    });
+                }
+            },
+        );
+    }
+
+    /// Stop the running search, if any, without playing its move.
+    fn stop_thinking(&mut self) {
+        self.search_id += 1;
+        self.engine.stop();
+    }
+
+    /// Send the result if the game is over, claiming draws by the fifty move rule and threefold
+    /// repetition. Returns whether it is.
+    fn report_outcome(&self) -> bool {
+        let Some(outcome) = self.bitboard.get_outcome(&self.history, true) else {
+            return false;
+        };
+        let comment = match (outcome.termination, outcome.winner) {
+            (Termination::Checkmate, Some(Color::White)) => "White mates",
+            (Termination::Checkmate, _) => "Black mates",
+            (Termination::Stalemate, _) => "Stalemate",
+            (Termination::InsufficientMaterial, _) => "Insufficient material",
+            (Termination::FiftyMoves | Termination::SeventyFiveMoves, _) => "Fifty move rule",
+            (Termination::ThreefoldRepetition | Termination::FivefoldRepetition, _) => {
+                "Draw by repetition"
+            }
+        };
+        send(
+            &self.output,
+            &format!("{} {{{}}}", outcome.result(), comment),
+        );
+        true
+    }
+}
+
+fn parse_arg<T: std::str::FromStr>(args: &[&str]) -> Result<T, XBoardError> {
+    let arg = args.first().ok_or(XBoardError::MissingArgument)?;
+    arg.parse()
+        .map_err(|_| XBoardError::InvalidArgument(arg.to_string()))
+}
+
+/// The thinking output line for `info`: depth, score, time in centiseconds, nodes and PV.
+pub fn format_thinking(info: &SearchInfo) -> String {
+    let score = if is_mate_score(info.score) {
+        let moves = (MATE_SCORE - info.score.abs() + 1) / 2;
+        (XBOARD_MATE_SCORE + moves) * info.score.signum()
+    } else {
+        info.score
+    };
+    let pv: Vec<String> = info.pv.iter().map(|_move| _move.to_string()).collect();
+    format!(
+        "{} {} {} {} {}",
+        info.depth,
+        score,
+        info.elapsed.as_millis() / 10,
+        info.nodes,
+        pv.join(" ")
+    )
+}
+
+/// Speak the Chess Engine Communication Protocol over `input` and `output` until the GUI quits
+/// or closes `input`. Commands are read on a separate thread so that they can interrupt a
+/// search.
+pub fn run<R: BufRead + Send + 'static>(input: R, output: Output) {
+    let (sender, receiver) = mpsc::channel();
+    let command_sender = sender.clone();
+    thread::spawn(move || {
+        for line in input.lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if command_sender.send(Message::Command(line)).is_err() {
+                return;
+            }
+        }
+        let _ = command_sender.send(Message::Command("quit".to_string()));
+    });
+
+    let mut xboard = XBoard::new(output, sender);
+    for message in receiver {
+        if !xboard.handle_message(message) {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use std::sync::{mpsc::Receiver, Mutex};
+
+    struct Session {
+        xboard: XBoard,
+        receiver: Receiver<Message>,
+        buffer: Arc<Mutex<Vec<u8>>>,
+    }
+
+    impl Session {
+        fn new() -> Session {
+            let buffer = Arc::new(Mutex::new(Vec::new()));
+            let (sender, receiver) = mpsc::channel();
+            Session {
+                xboard: XBoard::new(buffer.clone(), sender),
+                receiver,
+                buffer,
+            }
+        }
+
+        /// Send `command`, let any search it starts finish and return the lines written.
+        fn send(&mut self, command: &str) -> Vec<String> {
+            self.xboard.handle_command(command);
+            self.xboard.engine.wait();
+            for message in self.receiver.try_iter().collect::<Vec<_>>() {
+                self.xboard.handle_message(message);
+            }
+
+            let output = String::from_utf8(std::mem::take(&mut *self.buffer.lock().unwrap()));
+            output.unwrap().lines().map(str::to_string).collect()
+        }
+    }
+
+    #[test]
+    fn test_set_level() {
+        let mut session = Session::new();
+        session.send("level 40 2:30 0");
+        assert_eq!(session.xboard.base_time, Duration::from_secs(150));
+        assert_eq!(session.xboard.increment, Duration::ZERO);
+
+        session.send("level 0 5 1.5");
+        assert_eq!(session.xboard.moves_per_session, 0);
+        assert_eq!(session.xboard.base_time, Duration::from_secs(300));
+        assert_eq!(session.xboard.increment, Duration::from_millis(1500));
+        let limits = session.xboard.limits(Color::Black);
+        assert_eq!(limits.black_time, Some(Duration::from_secs(300)));
+        assert_eq!(limits.moves_to_go, None);
+
+        session.send("time 1234");
+        session.send("otim 500");
+        let limits = session.xboard.limits(Color::White);
+        assert_eq!(limits.white_time, Some(Duration::from_millis(12340)));
+        assert_eq!(limits.black_time, Some(Duration::from_secs(5)));
+
+        // A full move number of 0 counts as the first move
+        session.send("level 40 5 0");
+        session.send("setboard 4k3/8/8/8/8/8/8/4K3 w - - 0 0");
+        assert_eq!(session.xboard.limits(Color::White).moves_to_go, Some(40));
+
+        assert_eq!(
+            session.send("level 40 five 0"),
+            vec!["Error (invalid argument five): level 40 five 0"]
+        );
+    }
+
+    #[test]
+    fn test_xboard_game() {
+        let mut session = Session::new();
+        session.send("xboard");
+        let features = session.send("protover 2");
+        assert!(features[0].starts_with("feature ") && features[0].ends_with("done=1"));
+        assert_eq!(session.send("ping 7"), vec!["pong 7"]);
+
+        // The engine plays black and answers straight away
+        session.send("new");
+        session.send("sd 2");
+        session.send("post");
+        let output = session.send("usermove e2e4");
+        assert_eq!(output.len(), 3);
+        assert!(output[0].starts_with("1 ") && output[1].starts_with("2 "));
+        let reply = output[2].strip_prefix("move ").unwrap();
+        assert_eq!(session.xboard.undo_stack.len(), 2);
+        assert_eq!(session.xboard.undo_stack[1].0.to_string(), reply);
+
+        assert_eq!(session.send("usermove e2e5"), vec!["Illegal move: e2e5"]);
+        assert_eq!(
+            session.send("frobnicate"),
+            vec!["Error (unknown command): frobnicate"]
+        );
+
+        session.send("remove");
+        assert_eq!(session.xboard.bitboard.to_str(), STARTING_POSITION_FEN);
+        assert_eq!(session.send("undo"), vec!["Error (no move to undo): undo"]);
+
+        // In force mode the engine only records moves
+        session.send("force");
+        assert_eq!(session.send("usermove e2e4"), Vec::<String>::new());
+        assert_eq!(session.send("usermove e7e5"), Vec::<String>::new());
+        session.send("undo");
+        assert_eq!(session.xboard.undo_stack.len(), 1);
+    }
+
+    #[test]
+    fn test_result_reporting() {
+        let mut session = Session::new();
+        session.send("setboard 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        assert_eq!(session.send("go"), vec!["move a1a8", "1-0 {White mates}"]);
+
+        session.send("new");
+        session.send("setboard 7k/8/6Q1/8/8/8/8/K7 w - - 0 1");
+        assert_eq!(session.send("usermove g6f7"), vec!["1/2-1/2 {Stalemate}"]);
+        assert_eq!(
+            session.send("setboard 8/8/8"),
+            vec!["Error (FEN has the wrong number of fields: 1.): setboard 8/8/8"]
+        );
+
+        // Impossible positions are refused and the previous one kept
+        let kingless = "setboard 8/8/8/8/8/8/8/8 w - - 0 1";
+        assert_eq!(
+            session.send(kingless),
+            vec![format!(
+                "Error (FEN describes an impossible position: \
+                 [IncorrectKingCount(White, 0), IncorrectKingCount(Black, 0)].): {}",
+                kingless
+            )]
+        );
+        assert!(session.send("setboard R5k1/8/8/8/8/8/8/6K1 w - - 0 1")[0].starts_with("Error"));
+        assert_eq!(
+            session.xboard.bitboard.to_str(),
+            "7k/5Q2/8/8/8/8/8/K7 b - - 1 1"
+        );
+    }
+}
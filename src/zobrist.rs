@@ -0,0 +1,94 @@
+use lazy_static::lazy_static;
+
+use crate::utils::enums::{Color, Piece, Square};
+
+const ZOBRIST_SEED: u64 = 0x9E37_79B9_7F4A_7C15;
+
+struct ZobristKeys {
+    pieces: [[u64; 64]; 12],
+    castling_rights: [u64; 16],
+    en_passant_files: [u64; 8],
+    black_to_move: u64,
+}
+
+lazy_static! {
+    static ref ZOBRIST_KEYS: ZobristKeys = generate_zobrist_keys();
+}
+
+fn generate_zobrist_keys() -> ZobristKeys {
+    let mut rng = fastrand::Rng::with_seed(ZOBRIST_SEED);
+
+    let mut keys = ZobristKeys {
+        pieces: [[0; 64]; 12],
+        castling_rights: [0; 16],
+        en_passant_files: [0; 8],
+        black_to_move: 0,
+    };
+    for piece_keys in keys.pieces.iter_mut() {
+        for key in piece_keys.iter_mut() {
+            *key = rng.u64(..);
+        }
+    }
+    // No castling rights hash to zero so a position without rights only depends on its pieces.
+    for key in keys.castling_rights.iter_mut().skip(1) {
+        *key = rng.u64(..);
+    }
+    for key in keys.en_passant_files.iter_mut() {
+        *key = rng.u64(..);
+    }
+    keys.black_to_move = rng.u64(..);
+
+    keys
+}
+
+pub fn get_piece_key(color: Color, piece: Piece, square: Square) -> u64 {
+    ZOBRIST_KEYS.pieces[(color as usize) * 6 + (piece as usize)][square as usize]
+}
+
+/// Key for a whole castling rights mask, so changing rights XORs out the old mask and XORs in
+/// the new one.
+pub fn get_castling_rights_key(castling_rights: u8) -> u64 {
+    ZOBRIST_KEYS.castling_rights[(castling_rights & 0xF) as usize]
+}
+
+/// Only the file of the en passant square is hashed; the rank follows from the side to move.
+pub fn get_en_passant_key(square: Square) -> u64 {
+    ZOBRIST_KEYS.en_passant_files[square as usize % 8]
+}
+
+pub fn get_black_to_move_key() -> u64 {
+    ZOBRIST_KEYS.black_to_move
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use strum::IntoEnumIterator;
+
+    #[test]
+    fn test_keys_are_distinct() {
+        let mut keys = HashSet::new();
+        for color in Color::iter() {
+            for piece in Piece::iter() {
+                for square in Square::iter() {
+                    keys.insert(get_piece_key(color, piece, square));
+                }
+            }
+        }
+        for castling_rights in 1..16 {
+            keys.insert(get_castling_rights_key(castling_rights));
+        }
+        for square in [Square::A3, Square::D3, Square::H6] {
+            keys.insert(get_en_passant_key(square));
+        }
+        keys.insert(get_black_to_move_key());
+
+        assert_eq!(keys.len(), 12 * 64 + 15 + 3 + 1);
+        assert_eq!(get_castling_rights_key(0), 0);
+        assert_eq!(
+            get_en_passant_key(Square::E3),
+            get_en_passant_key(Square::E6)
+        );
+    }
+}